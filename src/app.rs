@@ -18,12 +18,17 @@ pub fn cli() -> App<'static, 'static> {
                 .long("--from-shell-wrapper")
                 .hidden(true)
         )
+        .arg(
+            Arg::with_name("set-env")
+                .long("--set-env")
+                .hidden(true)
+        )
         .subcommand(
             SubCommand::with_name("open")
                 .about("Opens a workspace")
                 .arg(
                     Arg::with_name("NAME")
-                        .help("Name of the workspace to open")
+                        .help("Name of the workspace to open, append @BRANCH to open a git worktree")
                         .required(true),
                 )
                 .arg(
@@ -33,6 +38,69 @@ pub fn cli() -> App<'static, 'static> {
                         .long("directory"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("close")
+                .about("Runs the on_leave hooks of a workspace and stops what it started")
+                .arg(
+                    Arg::with_name("NAME")
+                        .help("Name of the workspace to close, defaults to the one containing the current directory"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Shows the background commands of a workspace and whether they run")
+                .arg(
+                    Arg::with_name("NAME")
+                        .help("Name of the workspace, defaults to the one containing the current directory"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Runs a task of a workspace")
+                .usage("ws run [NAME] <TASK> [-- <ARGS>...]")
+                .arg(
+                    Arg::with_name("NAME")
+                        .help("Name of the workspace, defaults to the one containing the current directory")
+                        .required(true),
+                )
+                .arg(Arg::with_name("TASK").help("Name of the task to run"))
+                .arg(
+                    Arg::with_name("ARGS")
+                        .help("Arguments passed on to the task")
+                        .multiple(true)
+                        .last(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("Runs the watch commands of a workspace when files change")
+                .arg(
+                    Arg::with_name("NAME")
+                        .help("Name of the workspace to watch, append @BRANCH for a git worktree")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("current")
+                .about("Prints the name of the workspace containing the current directory"),
+        )
+        .subcommand(
+            SubCommand::with_name("prompt")
+                .about("Prints the name of the current workspace for a shell prompt, or nothing"),
+        )
+        .subcommand(
+            SubCommand::with_name("enter")
+                .about("Applies on_enter when the shell enters a workspace, used by `--on-cd`")
+                .setting(AppSettings::Hidden),
+        )
+        .subcommand(
+            SubCommand::with_name("supervise")
+                .about("Restarts a background command when it exits, used by `ws open`")
+                .setting(AppSettings::Hidden)
+                .arg(Arg::with_name("SHELL").required(true))
+                .arg(Arg::with_name("COMMAND").required(true))
+                .arg(Arg::with_name("always").long("always")),
+        )
         .subcommand(
             SubCommand::with_name("add")
                 .alias("new")
@@ -48,8 +116,7 @@ pub fn cli() -> App<'static, 'static> {
                 .about("Edits a workspace")
                 .arg(
                     Arg::with_name("NAME")
-                        .help("Name of the workspace to edit")
-                        .required(true),
+                        .help("Name of the workspace to edit, defaults to the one containing the current directory"),
                 ),
         )
         .subcommand(
@@ -67,6 +134,23 @@ pub fn cli() -> App<'static, 'static> {
                         .required(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("move")
+                .about("Changes the path of a workspace")
+                .arg(
+                    Arg::with_name("NAME")
+                        .help("Name of the workspace to move")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("NEW_PATH")
+                        .help("New path of the workspace, defaults to the current directory"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fix")
+                .about("Finds workspaces whose path was moved and updates them"),
+        )
         .subcommand(
             SubCommand::with_name("delete")
                 .alias("remove")
@@ -87,7 +171,99 @@ pub fn cli() -> App<'static, 'static> {
         .subcommand(
             SubCommand::with_name("list")
                 .alias("ls")
-                .about("Lists all workspaces"),
+                .about("Lists all workspaces")
+                .arg(
+                    Arg::with_name("sort")
+                        .long("sort")
+                        .short("s")
+                        .takes_value(true)
+                        .possible_values(&["name", "path"])
+                        .help("Sorts workspaces by name or path, defaults to list.sort"),
+                )
+                .arg(
+                    Arg::with_name("git")
+                        .long("git")
+                        .short("g")
+                        .help("Shows the branch, state and ahead/behind counts of git repositories"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Exports workspaces to a bundle with paths relative to the home directory")
+                .arg(
+                    Arg::with_name("NAMES")
+                        .help("Names of the workspaces to export, defaults to all")
+                        .multiple(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Writes the bundle to a file instead of printing it"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Imports workspaces from a bundle")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Bundle created by `ws export`")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("conflict")
+                        .long("on-conflict")
+                        .short("c")
+                        .takes_value(true)
+                        .possible_values(&["skip", "rename", "overwrite"])
+                        .default_value("skip")
+                        .help("What to do with workspaces whose name already exists"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short("y")
+                        .help("Skips confirmation prompt"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .alias("lint")
+                .about("Checks all workspaces for problems")
+                .long_about(
+                    "Checks all workspaces for problems and exits with a non-zero code if there are any",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Gets, sets or edits global settings")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Prints a setting")
+                        .arg(
+                            Arg::with_name("KEY")
+                                .help("Name of the setting, e.g. list.sort")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Changes a setting")
+                        .arg(
+                            Arg::with_name("KEY")
+                                .help("Name of the setting, e.g. list.sort")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("VALUE")
+                                .help("New value of the setting in YAML, or null to reset it")
+                                .required(true),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("edit").about("Edits the configuration file")),
         )
         .subcommand({
             SubCommand::with_name("shell")
@@ -98,6 +274,33 @@ pub fn cli() -> App<'static, 'static> {
                         .about("Returns a bash function to source in your bashrc")
                         .long_about(
                             "Returns a bash function to source in your bashrc with \nsource <(workspace shell bash)"
+                        )
+                        .arg(
+                            Arg::with_name("prompt")
+                                .long("prompt")
+                                .help("Also sets $WORKSPACE_NAME and $WORKSPACE_PATH, e.g. for your prompt"),
+                        )
+                        .arg(
+                            Arg::with_name("on-cd")
+                                .long("on-cd")
+                                .help("Also applies the on_enter section of a workspace when you cd into it"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("zsh")
+                        .about("Returns a zsh function to source in your zshrc")
+                        .long_about(
+                            "Returns a zsh function to source in your zshrc with \nsource <(workspace shell zsh)"
+                        )
+                        .arg(
+                            Arg::with_name("prompt")
+                                .long("prompt")
+                                .help("Also sets $WORKSPACE_NAME and $WORKSPACE_PATH, e.g. for your prompt"),
+                        )
+                        .arg(
+                            Arg::with_name("on-cd")
+                                .long("on-cd")
+                                .help("Also applies the on_enter section of a workspace when you cd into it"),
                         ),
                 )
                 .subcommand(
@@ -105,6 +308,16 @@ pub fn cli() -> App<'static, 'static> {
                         .about("Returns a fish function to source in your fish.config")
                         .long_about(
                             "Returns a fish function to source in your fish.config with \nworkspace shell fish | source -"
+                        )
+                        .arg(
+                            Arg::with_name("prompt")
+                                .long("prompt")
+                                .help("Also sets $WORKSPACE_NAME and $WORKSPACE_PATH, e.g. for your prompt"),
+                        )
+                        .arg(
+                            Arg::with_name("on-cd")
+                                .long("on-cd")
+                                .help("Also applies the on_enter section of a workspace when you cd into it"),
                         ),
                 )
                 .subcommand(
@@ -114,6 +327,11 @@ pub fn cli() -> App<'static, 'static> {
                         .about("Returns a PowerShell function to source in your shell profile")
                         .long_about(
                             "Returns a PowerShell function to source in your shell profile with \nInvoke-Expression \"$(workspace shell powershell)\""
+                        )
+                        .arg(
+                            Arg::with_name("prompt")
+                                .long("prompt")
+                                .help("Also sets $WORKSPACE_NAME and $WORKSPACE_PATH, e.g. for your prompt"),
                         ),
                 )
                 .subcommand(
@@ -122,6 +340,11 @@ pub fn cli() -> App<'static, 'static> {
                         .long_about(
                             "Creates a cmd batch file. Unless PATH is specified, it will be created in the same folder as the workspace binary",
                         )
+                        .arg(
+                            Arg::with_name("prompt")
+                                .long("prompt")
+                                .help("Also sets $WORKSPACE_NAME and $WORKSPACE_PATH, e.g. for your prompt"),
+                        )
                         .arg(Arg::with_name("PATH")),
                 )
         })
@@ -0,0 +1,203 @@
+use crate::config::Config;
+use crate::workspace::Browser;
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use colored::Colorize;
+
+/// Arguments that select a profile and open a new window, for browsers whose binary name
+/// contains the key
+const PRESETS: &[(&str, &str, &str)] = &[
+    ("firefox", "-P {profile}", "--new-window"),
+    ("librewolf", "-P {profile}", "--new-window"),
+    ("chrom", "--profile-directory={profile}", "--new-window"),
+    ("brave", "--profile-directory={profile}", "--new-window"),
+    ("vivaldi", "--profile-directory={profile}", "--new-window"),
+    ("edge", "--profile-directory={profile}", "--new-window"),
+];
+
+/// Programs that open only one URL per invocation, so `together` cannot apply
+const SINGLE_URL: &[&str] = &["xdg-open", "explorer"];
+
+/// Returns the argument list that opens tabs: the workspace's `command`, the global setting,
+/// $BROWSER or the program that opens URLs on this system
+pub fn template(browser: Option<&Browser>) -> Vec<String> {
+    if let Some(browser) = browser.filter(|browser| !browser.command.is_empty()) {
+        return browser.command.clone();
+    }
+    let program = Config::get()
+        .browser
+        .clone()
+        .or_else(|| env::var("BROWSER").ok())
+        .unwrap_or_else(|| {
+            let opener = if cfg!(target_os = "macos") {
+                "open"
+            } else if cfg!(windows) {
+                "explorer"
+            } else {
+                "xdg-open"
+            };
+            opener.to_string()
+        });
+    vec![program]
+}
+
+/// Builds the processes that open the tabs, a single one if `together` is set. `{url}` in the
+/// template is replaced by the tabs, which are appended otherwise.
+pub fn commands(browser: Option<&Browser>, tabs: &[&str]) -> Vec<Command> {
+    if tabs.is_empty() {
+        return Vec::new();
+    }
+    let mut template = template(browser);
+    if let Some(browser) = browser {
+        template = with_options(template, browser);
+    }
+    if !template.iter().any(|arg| arg.contains("{url}")) {
+        template.push("{url}".to_string());
+    }
+
+    let mut together = browser.is_some_and(|browser| browser.together);
+    let program = template.first().map_or("", |program| program_name(program));
+    if together && SINGLE_URL.contains(&program) {
+        if tabs.len() > 1 {
+            warn!(
+                "{} opens one URL at a time, so the tabs open separately",
+                program
+            );
+        }
+        together = false;
+    }
+    // An argument like `--app={url}` can hold only one URL
+    if together && template.iter().any(|arg| arg != "{url}" && arg.contains("{url}")) {
+        if tabs.len() > 1 {
+            warn!("{{url}} is part of an argument, so the tabs open separately");
+        }
+        together = false;
+    }
+    let groups: Vec<Vec<&str>> = if together {
+        vec![tabs.to_vec()]
+    } else {
+        tabs.iter().map(|tab| vec![*tab]).collect()
+    };
+    groups
+        .into_iter()
+        .map(|urls| {
+            let mut args = template.iter().flat_map(|arg| {
+                if arg == "{url}" {
+                    urls.iter().map(|url| url.to_string()).collect()
+                } else {
+                    // Groups are never empty, and have one tab if an argument embeds {url}
+                    vec![arg.replace("{url}", urls[0])]
+                }
+            });
+            let mut process = Command::new(args.next().unwrap_or_default());
+            process.args(args);
+            process
+        })
+        .collect()
+}
+
+/// Returns the name of a program without its directory and extension, e.g. `firefox`
+fn program_name(program: &str) -> &str {
+    Path::new(program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program)
+}
+
+/// Adds the arguments for `profile` and `new_window` after the program, unless the template
+/// already contains `{profile}`
+fn with_options(template: Vec<String>, browser: &Browser) -> Vec<String> {
+    let program = template.first().cloned().unwrap_or_default();
+    let name = program_name(&program).to_lowercase();
+    let preset = PRESETS.iter().find(|(key, _, _)| name.contains(key));
+
+    let mut options: Vec<String> = Vec::new();
+    if let Some(profile) = &browser.profile {
+        let has_placeholder = template.iter().any(|arg| arg.contains("{profile}"));
+        match preset {
+            _ if has_placeholder => {}
+            Some((_, args, _)) => options.extend(args.split_whitespace().map(str::to_string)),
+            None => warn!(
+                "Cannot select a profile for {}, add {{profile}} to `browser.command`",
+                program
+            ),
+        }
+        options = options
+            .into_iter()
+            .map(|arg| arg.replace("{profile}", profile))
+            .collect();
+    }
+    if browser.new_window {
+        match preset {
+            Some((_, _, flag)) => options.push(flag.to_string()),
+            None => warn!("Cannot open a new window of {}", program),
+        }
+    }
+
+    let profile = browser.profile.as_deref().unwrap_or_default();
+    let mut args = template
+        .into_iter()
+        .map(|arg| arg.replace("{profile}", profile));
+    let mut template: Vec<String> = args.next().into_iter().collect();
+    template.extend(options);
+    template.extend(args);
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser(command: &[&str], together: bool) -> Browser {
+        Browser {
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            together,
+            ..Default::default()
+        }
+    }
+
+    fn args(process: &Command) -> Vec<String> {
+        let program = process.get_program().to_string_lossy().to_string();
+        let args = process
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string());
+        std::iter::once(program).chain(args).collect()
+    }
+
+    #[test]
+    fn no_tabs() {
+        assert!(commands(Some(&browser(&["firefox"], true)), &[]).is_empty());
+        assert!(commands(Some(&browser(&["firefox"], false)), &[]).is_empty());
+    }
+
+    #[test]
+    fn together() {
+        let processes = commands(Some(&browser(&["firefox"], true)), &["a.com", "b.com"]);
+        assert_eq!(processes.len(), 1);
+        assert_eq!(args(&processes[0]), ["firefox", "a.com", "b.com"]);
+
+        let processes = commands(
+            Some(&browser(&["/usr/bin/xdg-open"], true)),
+            &["a.com", "b.com"],
+        );
+        assert_eq!(processes.len(), 2);
+        assert_eq!(args(&processes[1]), ["/usr/bin/xdg-open", "b.com"]);
+    }
+
+    #[test]
+    fn url_placeholder() {
+        let processes = commands(Some(&browser(&["open", "-u", "{url}"], false)), &["a.com"]);
+        assert_eq!(args(&processes[0]), ["open", "-u", "a.com"]);
+
+        let processes = commands(
+            Some(&browser(&["chromium", "--app={url}"], true)),
+            &["a.com", "b.com"],
+        );
+        assert_eq!(processes.len(), 2);
+        assert_eq!(args(&processes[0]), ["chromium", "--app=a.com"]);
+        assert_eq!(args(&processes[1]), ["chromium", "--app=b.com"]);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::workspace::Workspace;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Workspaces by name. Their paths below the home directory start with `~`, so that they can
+/// be moved between machines.
+pub type Bundle = BTreeMap<String, Workspace>;
+
+pub enum Conflict {
+    Skip,
+    Rename,
+    Overwrite,
+}
+
+pub fn read(path: &Path) -> Result<Bundle, String> {
+    let content = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let bundle: Bundle = serde_yaml::from_str(&content).map_err(|error| error.to_string())?;
+    match bundle.keys().find(|name| !is_valid_name(name)) {
+        Some(name) => Err(format!("'{}' is not a valid workspace name", name)),
+        None => Ok(bundle),
+    }
+}
+
+/// Names become file names, so they must not be empty or lead out of the workspace directory
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && !name.contains('\\') && !name.contains("..")
+}
+
+pub fn write(bundle: &Bundle) -> String {
+    serde_yaml::to_string(bundle).unwrap()
+}
+
+/// Returns `name` followed by the lowest number from 2 that is neither a workspace nor taken
+pub fn unique_name(name: &str, taken: &[String]) -> String {
+    (2..)
+        .map(|number| format!("{}-{}", name, number))
+        .find(|name| !Workspace::exists(name) && !taken.contains(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name("blog"));
+        assert!(is_valid_name("blog-2.0"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("../blog"));
+        assert!(!is_valid_name("notes/blog"));
+        assert!(!is_valid_name("notes\\blog"));
+        assert!(!is_valid_name(".."));
+    }
+}
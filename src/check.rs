@@ -0,0 +1,199 @@
+use crate::browser;
+use crate::service;
+use crate::terminal;
+use crate::tilde::Tilde;
+use crate::workspace::{Command, Restart, Tab, Terminal, Workspace};
+
+use std::env;
+use std::path::Path;
+
+/// Shell builtins and keywords that cannot be found on $PATH
+const BUILTINS: &[&str] = &[
+    ".", ":", "[", "alias", "bg", "cd", "echo", "eval", "exec", "exit", "export", "false", "fg",
+    "if", "jobs", "printf", "pwd", "read", "set", "source", "test", "true", "type", "unset",
+];
+
+/// Returns a description of every problem found in a workspace
+pub fn check(ws: &Workspace) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+
+    if !ws.path.exists() {
+        problems.push(format!(
+            "the path '{}' does not exist",
+            ws.path.tilde_format()
+        ));
+    }
+
+    let commands = ws
+        .commands
+        .local
+        .iter()
+        .chain(&ws.commands.external)
+        .chain(&ws.commands.background);
+    for command in commands {
+        let dir = command.dir(&ws.path);
+        if ws.path.exists() && !dir.exists() {
+            problems.push(format!(
+                "the directory '{}' of `{}` does not exist",
+                dir.tilde_format(),
+                command.label()
+            ));
+        }
+    }
+    for command in ws.commands.local.iter().chain(&ws.commands.external) {
+        let details = match command.details() {
+            Some(details) => details,
+            None => continue,
+        };
+        let is_background = details.name.is_some()
+            || !details.depends_on.is_empty()
+            || details.wait_for_port.is_some()
+            || details.wait_for_file.is_some()
+            || details.wait_for_log.is_some()
+            || !details.ports.is_empty()
+            || details.restart != Restart::Never;
+        if is_background {
+            problems.push(format!(
+                "`{}` has options that only apply to background commands",
+                command.label()
+            ));
+        }
+    }
+
+    let commands = ws
+        .commands
+        .local
+        .iter()
+        .chain(&ws.commands.external)
+        .chain(&ws.commands.background)
+        .map(Command::run)
+        .chain(ws.hooks.pre_open.iter().map(String::as_str))
+        .chain(ws.hooks.post_open.iter().map(String::as_str))
+        .chain(ws.hooks.on_leave.iter().map(String::as_str))
+        .chain(ws.tasks.values().map(String::as_str))
+        .chain(ws.watch.values().map(String::as_str));
+    for command in commands {
+        if let Some(program) = program(command) {
+            if !BUILTINS.contains(&program) && !is_executable(program, &ws.path) {
+                problems.push(format!("`{}` is not on $PATH", program));
+            }
+        }
+    }
+
+    for pattern in ws.watch.keys() {
+        if let Err(error) = glob::Pattern::new(pattern) {
+            problems.push(format!(
+                "'{}' is not a valid pattern: {}",
+                pattern, error.msg
+            ));
+        }
+    }
+
+    if let Err(message) = service::order(&ws.commands.background) {
+        problems.push(message);
+    }
+
+    let terminal = terminal::resolve(ws.terminal.as_ref());
+    if !ws.commands.external.is_empty() {
+        let program = match &terminal {
+            Some(Terminal::Tmux) => Some("tmux"),
+            Some(Terminal::Template(template)) => template.first().map(String::as_str),
+            None => None,
+        };
+        if let Some(program) = program {
+            if !is_executable(program, &ws.path) {
+                problems.push(format!("`{}` is not on $PATH", program));
+            }
+        }
+    }
+
+    if !ws.tabs.is_empty() {
+        let template = browser::template(ws.browser.as_ref());
+        if let Some(program) = template.first() {
+            if !is_executable(program, &ws.path) {
+                problems.push(format!("`{}` is not on $PATH", program));
+            }
+        }
+    }
+
+    let variables = [
+        (
+            "TERMINAL",
+            terminal.is_none() && !ws.commands.external.is_empty(),
+        ),
+        ("SHELL", !ws.commands.background.is_empty()),
+    ];
+    for (variable, used) in variables.iter() {
+        if *used && env::var_os(variable).is_none() {
+            problems.push(format!("${} is not set", variable));
+        }
+    }
+
+    for tab in ws.tabs.iter().map(Tab::url) {
+        if !is_url(tab) {
+            problems.push(format!("'{}' is not a valid URL", tab));
+        }
+    }
+
+    problems
+}
+
+/// Returns the program a shell command runs, skipping variable assignments
+fn program(command: &str) -> Option<&str> {
+    command.split_whitespace().find(|word| !word.contains('='))
+}
+
+fn is_executable(program: &str, dir: &Path) -> bool {
+    if program.contains('/') || program.contains('\\') {
+        return dir.join(program).exists();
+    }
+    let paths = match env::var_os("PATH") {
+        Some(paths) => paths,
+        None => return false,
+    };
+    env::split_paths(&paths).any(|path| {
+        let path = path.join(program);
+        path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file())
+    })
+}
+
+fn is_url(tab: &str) -> bool {
+    if tab.is_empty() || tab.chars().any(char::is_whitespace) {
+        return false;
+    }
+    match tab.find("://") {
+        Some(index) => {
+            let (scheme, rest) = (&tab[..index], &tab[index + 3..]);
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && !rest.is_empty()
+        }
+        // Browsers accept URLs without a scheme, e.g. `localhost`
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert!(is_url("https://example.com/path?query"));
+        assert!(is_url("localhost:8080"));
+        assert!(is_url("git+ssh://host"));
+        assert!(!is_url(""));
+        assert!(!is_url("https://"));
+        assert!(!is_url("has space.com"));
+        assert!(!is_url("1http://example.com"));
+    }
+
+    #[test]
+    fn programs() {
+        assert_eq!(program("cargo check"), Some("cargo"));
+        assert_eq!(program("RUST_LOG=debug cargo run"), Some("cargo"));
+        assert_eq!(program("  "), None);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::editor;
+use crate::exit::Exit;
+use crate::tilde::Tilde;
+use crate::workspace::Terminal;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+
+use colored::Colorize;
+use serde_derive::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings that `ws config` can get and set
+pub const KEYS: &[&str] = &[
+    "directory",
+    "search_paths",
+    "editor",
+    "browser",
+    "terminal",
+    "confirm",
+    "color",
+    "list.sort",
+    "list.git",
+];
+
+/// Global settings, read from `config.yaml` next to the workspaces. They take precedence over
+/// the environment variables $EDITOR, $VISUAL, $BROWSER and $TERMINAL. The exception is
+/// `directory`, which $WORKSPACE_DIR overrides.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Where workspaces are stored instead of the configuration directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+    /// More folders to read workspaces from, e.g. a repository shared by a team
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_paths: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal: Option<Terminal>,
+    /// Whether to ask before deleting or creating duplicate workspaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub color: Color,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub list: List,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// Respects $NO_COLOR, $CLICOLOR and $CLICOLOR_FORCE
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Defaults for `ws list`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct List {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sort: Sort,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub git: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Name,
+    Path,
+}
+
+impl Config {
+    /// Returns the configuration, which is read once and defaults if there is no file
+    pub fn get() -> &'static Config {
+        CONFIG.get_or_init(|| {
+            let path = Self::file_path();
+            if !path.exists() {
+                return Config::default();
+            }
+            Self::parse(&path).unwrap_or_else(|error| {
+                error.report();
+                process::exit(1)
+            })
+        })
+    }
+
+    pub fn parse(path: &Path) -> Result<Config, Error> {
+        let content = fs::read_to_string(path).map_err(Error::Read)?;
+        serde_yaml::from_str(&content).map_err(Error::Parse)
+    }
+
+    pub fn write(&self) {
+        const ERR_MESSAGE: &str = "Could not write configuration";
+
+        let path = Self::file_path();
+        // Safe to unwrap here, because file_path() has a parent
+        fs::create_dir_all(path.parent().unwrap()).unwrap_or_exit(ERR_MESSAGE);
+        let serialized = serde_yaml::to_string(self).unwrap();
+        fs::write(path, serialized).unwrap_or_exit(ERR_MESSAGE);
+    }
+
+    /// Returns the value of a key like `list.sort` as YAML, or `None` if it is not set
+    pub fn value(&self, key: &str) -> Option<Value> {
+        let mut value = serde_yaml::to_value(self).unwrap();
+        for part in key.split('.') {
+            value = value.get(part)?.clone();
+        }
+        Some(value)
+    }
+
+    /// Returns a copy of the configuration in which a key like `list.sort` is set to a value,
+    /// which is parsed as YAML, or reset if the value is `null`
+    pub fn with(&self, key: &str, value: &str) -> Result<Config, serde_yaml::Error> {
+        let value: Value = serde_yaml::from_str(value)?;
+        let mut config = serde_yaml::to_value(self).unwrap();
+
+        let mut parts: Vec<&str> = key.split('.').collect();
+        // Safe to unwrap here, because split() returns at least one part
+        let last = parts.pop().unwrap();
+        let mut table = &mut config;
+        for part in parts {
+            let mapping = table.as_mapping_mut().unwrap();
+            table = mapping
+                .entry(Value::from(part))
+                .or_insert_with(|| Value::Mapping(Default::default()));
+        }
+        let table = table.as_mapping_mut().unwrap();
+        // Removing a key restores its default
+        if value.is_null() {
+            table.remove(&Value::from(last));
+        } else {
+            table.insert(Value::from(last), value);
+        }
+
+        serde_yaml::from_value(config)
+    }
+
+    pub fn edit() {
+        let path = Self::file_path();
+        if !path.exists() {
+            Config::default().write();
+        }
+        // Fixing an invalid configuration must be possible, so it falls back to the defaults
+        CONFIG.get_or_init(|| Self::parse(&path).unwrap_or_default());
+        editor::edit(&path, |temp| match Self::parse(temp) {
+            Ok(_) => true,
+            Err(error) => {
+                error.report();
+                false
+            }
+        });
+    }
+
+    /// Returns the `directory` setting with `~` and variables expanded
+    pub fn directory(&self) -> Option<PathBuf> {
+        self.directory.as_deref().map(Self::resolve)
+    }
+
+    /// Returns the `search_paths` setting with `~` and variables expanded
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        self.search_paths
+            .iter()
+            .map(|path| Self::resolve(path))
+            .collect()
+    }
+
+    /// Expands a path from the configuration, which is relative to the configuration file
+    fn resolve(path: &Path) -> PathBuf {
+        let path = path.tilde_expand();
+        if path.is_relative() {
+            // Safe to unwrap here, because file_path() has a parent
+            Self::file_path().parent().unwrap().join(path)
+        } else {
+            path
+        }
+    }
+
+    pub fn file_path() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_exit("Could not find configuration directory");
+        path.push("workspace");
+        path.push("config.yaml");
+        path
+    }
+}
+
+pub enum Error {
+    Read(std::io::Error),
+    Parse(serde_yaml::Error),
+}
+
+impl Error {
+    pub fn report(&self) {
+        let path = Config::file_path().tilde_format();
+        match self {
+            Error::Read(cause) => {
+                error!("Could not read configuration from {}", path);
+                indent_error!("{}", cause);
+            }
+            Error::Parse(cause) => {
+                error!("Could not parse configuration from {}", path);
+                indent_error!("{}", cause);
+            }
+        }
+    }
+}
+
+fn is_default<T: Default + PartialEq>(t: &T) -> bool {
+    t == &T::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get() {
+        let config = Config::default().with("list.sort", "path").unwrap();
+        assert_eq!(config.list.sort, Sort::Path);
+        assert_eq!(config.value("list.sort"), Some(Value::from("path")));
+        assert_eq!(config.value("editor"), None);
+
+        let config = config.with("editor", "vim -p").unwrap();
+        assert_eq!(config.editor.as_deref(), Some("vim -p"));
+        assert_eq!(config.list.sort, Sort::Path);
+    }
+
+    #[test]
+    fn reset_with_null() {
+        let config = Config::default().with("confirm", "false").unwrap();
+        assert_eq!(config.confirm, Some(false));
+        let config = config.with("confirm", "null").unwrap();
+        assert_eq!(config.confirm, None);
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let home = dirs::home_dir().unwrap();
+        let config = Config::default()
+            .with("search_paths", "[~/team, /srv/workspaces, shared]")
+            .unwrap();
+        let folder = Config::file_path().parent().unwrap().to_path_buf();
+        assert_eq!(
+            config.search_paths(),
+            [
+                home.join("team"),
+                PathBuf::from("/srv/workspaces"),
+                folder.join("shared")
+            ]
+        );
+        assert_eq!(config.directory(), None);
+        let config = config.with("directory", "~/workspaces").unwrap();
+        assert_eq!(config.directory(), Some(home.join("workspaces")));
+    }
+
+    #[test]
+    fn invalid_value() {
+        assert!(Config::default().with("color", "sometimes").is_err());
+        assert!(Config::default().with("list.git", "[1, 2]").is_err());
+    }
+}
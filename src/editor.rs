@@ -0,0 +1,121 @@
+use crate::config::Config;
+use crate::exit::Exit;
+use crate::tilde::Tilde;
+use crate::VERBOSE;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+
+/// Returns the editor from the configuration, $EDITOR or $VISUAL
+pub fn editor() -> String {
+    Config::get()
+        .editor
+        .clone()
+        .or_else(|| env::var("EDITOR").ok())
+        .or_else(|| env::var("VISUAL").ok())
+        .unwrap_or_exit("Please set $EDITOR or $VISUAL to edit workspaces")
+}
+
+/// Edits a copy of the file until `valid` accepts it or the user gives up, and only then
+/// replaces the file. `valid` is responsible for reporting errors.
+pub fn edit(path: &Path, valid: impl Fn(&Path) -> bool) {
+    let editor = editor();
+
+    let content = fs::read(path).unwrap_or_exit("Could not read file to edit");
+    let temp = temp_file(path, &content).unwrap_or_exit("Could not copy file to edit");
+
+    loop {
+        let mut args = editor.split_whitespace();
+        let result = process::Command::new(args.next().unwrap_or_default())
+            .args(args)
+            .arg(&temp)
+            .stdin(tty())
+            .stdout(tty())
+            .status();
+
+        match result {
+            Ok(status) if status.success() => {}
+            // Editors exit with an error to abort, e.g. `:cq` in vim
+            Ok(status) => {
+                fs::remove_file(&temp).ok();
+                println!(
+                    "Discarded changes to {}, because the editor exited with {}",
+                    path.tilde_format(),
+                    status
+                );
+                return;
+            }
+            Err(error) => {
+                fs::remove_file(&temp).ok();
+                error!("Could not run editor: {}", editor);
+                log!("{}", error);
+                process::exit(1);
+            }
+        }
+
+        if valid(&temp) {
+            break;
+        }
+        if !ask!("Edit the file again") {
+            fs::remove_file(&temp).ok();
+            println!("Discarded changes to {}", path.tilde_format());
+            return;
+        }
+    }
+
+    // Copy instead of renaming, because the temporary directory can be on another file system
+    fs::copy(&temp, path).unwrap_or_exit("Could not write edited file");
+    fs::remove_file(&temp).ok();
+}
+
+/// Creates a new file with the content in the temporary directory. Its name is unique and the
+/// file must not exist yet, so that a shared directory cannot redirect the write.
+fn temp_file(path: &Path, content: &[u8]) -> io::Result<PathBuf> {
+    // Safe to unwrap here, because only files are edited
+    let name = path.file_name().unwrap().to_string_lossy();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.subsec_nanos());
+    let mut attempt = 0;
+    loop {
+        let mut temp = env::temp_dir();
+        temp.push(format!(
+            "workspace-{}-{}-{}-{}",
+            process::id(),
+            nanos,
+            attempt,
+            name
+        ));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+        {
+            Ok(mut file) => {
+                file.write_all(content)?;
+                return Ok(temp);
+            }
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Opens the terminal, because stdout is read by the shell wrapper
+pub fn tty() -> Stdio {
+    let path = if cfg!(windows) { "CON" } else { "/dev/tty" };
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::inherit())
+}
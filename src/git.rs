@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Runs git in a directory and returns its trimmed output if it succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub struct Status {
+    /// The current branch, or `None` if the HEAD is detached
+    pub branch: Option<String>,
+    pub dirty: bool,
+    /// Commits ahead and behind the upstream branch, if there is one
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+/// Returns the status of the repository at `dir`, or `None` if it is not a repository
+pub fn status(dir: &Path) -> Option<Status> {
+    let output = git(dir, &["status", "--porcelain=v2", "--branch"])?;
+    parse_status(&output)
+}
+
+/// Parses the output of `git status --porcelain=v2 --branch`
+fn parse_status(output: &str) -> Option<Status> {
+    let mut status = Status {
+        branch: None,
+        dirty: false,
+        ahead_behind: None,
+    };
+
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            if head != "(detached)" {
+                status.branch = Some(head.to_string());
+            }
+        } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+            // The counts look like `+1 -2`
+            let mut counts = counts
+                .split_whitespace()
+                .map(|count| count[1..].parse().unwrap_or(0));
+            status.ahead_behind = Some((counts.next()?, counts.next()?));
+        } else if !line.starts_with('#') {
+            status.dirty = true;
+        }
+    }
+
+    Some(status)
+}
+
+/// Returns the URL of the `origin` remote
+pub fn remote(dir: &Path) -> Option<String> {
+    git(dir, &["remote", "get-url", "origin"]).filter(|url| !url.is_empty())
+}
+
+/// Clones a repository, letting git print its progress
+pub fn clone(url: &str, path: &Path) -> bool {
+    Command::new("git")
+        .arg("clone")
+        .arg(url)
+        .arg(path)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Returns the path of the worktree that has `branch` checked out
+pub fn worktree(dir: &Path, branch: &str) -> Option<PathBuf> {
+    let output = git(dir, &["worktree", "list", "--porcelain"])?;
+    let reference = format!("branch refs/heads/{}", branch);
+    let mut path: Option<&str> = None;
+    for line in output.lines() {
+        if let Some(worktree) = line.strip_prefix("worktree ") {
+            path = Some(worktree);
+        } else if line == reference {
+            return path.map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Creates a worktree at `path` that checks out `branch`, letting git print its progress
+pub fn add_worktree(dir: &Path, path: &Path, branch: &str) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["worktree", "add"])
+        .arg(path)
+        .arg(branch)
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Returns the name of a repository from its URL, e.g. `blog` for `git@host:user/blog.git`
+pub fn repository_name(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    let url = url.trim_end_matches(".git");
+    url.rsplit(['/', ':']).next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_names() {
+        assert_eq!(repository_name("git@github.com:user/blog.git"), "blog");
+        assert_eq!(repository_name("https://github.com/user/blog/"), "blog");
+        assert_eq!(repository_name("https://github.com/user/blog"), "blog");
+        assert_eq!(repository_name("/srv/git/blog.git"), "blog");
+        assert_eq!(repository_name("blog"), "blog");
+    }
+
+    #[test]
+    fn status_with_upstream() {
+        let output = "# branch.oid 1234\n\
+                      # branch.head main\n\
+                      # branch.upstream origin/main\n\
+                      # branch.ab +2 -3";
+        let status = parse_status(output).unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.ahead_behind, Some((2, 3)));
+        assert!(!status.dirty);
+    }
+
+    #[test]
+    fn status_detached_and_dirty() {
+        let output = "# branch.oid 1234\n\
+                      # branch.head (detached)\n\
+                      1 .M N... 100644 100644 100644 1234 1234 src/main.rs";
+        let status = parse_status(output).unwrap();
+        assert_eq!(status.branch, None);
+        assert_eq!(status.ahead_behind, None);
+        assert!(status.dirty);
+    }
+}
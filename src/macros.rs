@@ -4,30 +4,52 @@ macro_rules! run {
     };
 }
 
+// Dependencies: SET_ENV: bool
+macro_rules! set_env {
+    ($variable:expr, $value:expr) => {
+        unsafe {
+            if SET_ENV {
+                println!("SET>{}={}", $variable, $value);
+            }
+        }
+    };
+}
+
+// Dependencies: SET_ENV: bool
+macro_rules! unset_env {
+    ($variable:expr) => {
+        unsafe {
+            if SET_ENV {
+                println!("UNSET>{}", $variable);
+            }
+        }
+    };
+}
+
 // Dependencies: colored::Colorize
 macro_rules! error {
-    ($message:expr$(,$arg:expr)*) => {
+    ($message:expr$(,$arg:expr)*) => {{
         eprintln!(concat!("{} ", $message), "error:".red().bold()$(, $arg)*);
-    };
+    }};
 }
 
 macro_rules! indent_error {
-    ($message:expr$(,$arg:expr)*) => {
+    ($message:expr$(,$arg:expr)*) => {{
         eprintln!(concat!("       ", $message)$(, $arg)*);
-    };
+    }};
 }
 
 // Dependencies: colored::Colorize
 macro_rules! warn {
-    ($message:expr$(,$arg:expr)*) => {
+    ($message:expr$(,$arg:expr)*) => {{
         eprintln!(concat!("{} ", $message), "warning:".yellow().bold()$(, $arg)*);
-    };
+    }};
 }
 
 macro_rules! indent_warn {
-    ($message:expr$(,$arg:expr)*) => {
+    ($message:expr$(,$arg:expr)*) => {{
         eprintln!(concat!("         ", $message)$(, $arg)*);
-    };
+    }};
 }
 
 // Dependencies: VERBOSE: bool, colored::Colorize
@@ -73,12 +95,14 @@ macro_rules! skip_none {
     };
 }
 
-// Dependencies: warn!, confirm_once!
+// Dependencies: warn!, confirm_once!, Config (src/config.rs)
 macro_rules! confirm {
     ($confirm:stmt;) => {
-        loop {
-            $confirm
-            confirm_once!();
+        if $crate::config::Config::get().confirm != Some(false) {
+            loop {
+                $confirm
+                confirm_once!();
+            }
         }
     };
     ($prompt:expr$(,$arg:expr)*) => {
@@ -92,7 +116,7 @@ macro_rules! confirm {
 macro_rules! confirm_once {
     () => {
         let mut response = String::new();
-        ::std::io::stdin()
+        let read = ::std::io::stdin()
             .read_line(&mut response)
             .unwrap_or_exit("Could not read line");
         response = response.to_lowercase();
@@ -100,9 +124,29 @@ macro_rules! confirm_once {
         if response == "y" || response == "yes" {
             break;
         }
-        if response == "n" || response == "no" {
+        if read == 0 || response == "n" || response == "no" {
             println!("Aborting");
             return;
         }
     };
 }
+
+// Dependencies: warn!, Exit (src/exit.rs)
+macro_rules! ask {
+    ($prompt:expr$(,$arg:expr)*) => {
+        loop {
+            warn!(concat!($prompt, "? [y/n]")$(,$arg)*);
+            let mut response = String::new();
+            let read = ::std::io::stdin()
+                .read_line(&mut response)
+                .unwrap_or_exit("Could not read line");
+            let response: &str = &response.trim().to_lowercase();
+            if response == "y" || response == "yes" {
+                break true;
+            }
+            if read == 0 || response == "n" || response == "no" {
+                break false;
+            }
+        }
+    };
+}
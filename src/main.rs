@@ -1,35 +1,62 @@
 #[macro_use]
 mod macros;
 mod app;
+mod browser;
+mod bundle;
+mod check;
+mod config;
+mod editor;
 mod exit;
+mod git;
+mod relocate;
+mod service;
 mod shell;
+mod terminal;
 mod tilde;
+mod tmux;
+mod watch;
 mod workspace;
 
 use clap::ArgMatches;
 use colored::Colorize;
-use failure::Fail;
 
 use std::env;
 use std::fs;
 use std::io::Write;
 use std::path;
 use std::process;
+use std::thread;
 
+use crate::config::Config;
 use crate::exit::Exit;
 use crate::tilde::Tilde;
-use crate::workspace::Workspace;
+use crate::workspace::{OnEnter, Workspace};
 
 pub static mut VERBOSE: bool = false;
+/// Whether the shell wrapper sets environment variables from `SET>` lines
+pub static mut SET_ENV: bool = false;
 
 fn main() {
     let matches = app::cli().get_matches();
 
     unsafe {
         VERBOSE = matches.is_present("verbose");
+        SET_ENV = matches.is_present("set-env");
     }
 
-    if !matches.is_present("shell-wrapper") && matches.subcommand_matches("shell").is_none() {
+    // `ws config` must not fail on an invalid configuration, so that it can be fixed
+    if matches.subcommand_name() != Some("config") {
+        match Config::get().color {
+            config::Color::Auto => {}
+            config::Color::Always => colored::control::set_override(true),
+            config::Color::Never => colored::control::set_override(false),
+        }
+    }
+
+    let is_backend = ["shell", "supervise", "prompt", "watch"]
+        .iter()
+        .any(|name| matches.subcommand_matches(name).is_some());
+    if !matches.is_present("shell-wrapper") && !is_backend {
         warn!("You are using the workspace binary, which is the backend for the `ws` function.");
         indent_warn!(
             "To set `ws` up in your shell, see the README.md or run `workspace shell --help`"
@@ -38,31 +65,47 @@ fn main() {
 
     match matches.subcommand() {
         ("open", Some(matches)) => {
-            let name: &str = matches.value_of("NAME").unwrap();
-            let ws = Workspace::get(name)
-                .unwrap_or_exit(&format!("A workspace called '{}' does not exist", name))
-                .unwrap_or_else(|error| {
-                    let path = Workspace::file_path(name);
-                    error!("{} from {}", error, path.tilde_format());
-                    if let Some(cause) = error.cause() {
-                        indent_error!("{}", cause);
-                    }
-                    if let Some(backtrace) = error.backtrace() {
-                        log!("{}", backtrace);
-                    }
-                    process::exit(1)
-                });
+            let (name, branch) = Workspace::split_branch(matches.value_of("NAME").unwrap());
+            let mut ws = get_or_exit(name);
             if !ws.path.exists() {
-                error!("The location of this workspace does not exist anymore");
-                indent_error!("the path '{}' was moved or deleted", ws.path.tilde_format());
-                process::exit(1);
+                if let Some(repo) = &ws.repo {
+                    warn!("The location of this workspace does not exist");
+                    confirm!("Clone {} into {}", repo, ws.path.tilde_format());
+                    if !git::clone(repo, &ws.path) {
+                        error!("Could not clone {}", repo);
+                        process::exit(1);
+                    }
+                } else {
+                    error!("The location of this workspace does not exist anymore");
+                    indent_error!("the path '{}' was moved or deleted", ws.path.tilde_format());
+                    indent_error!("run `ws fix` or `ws move {} <PATH>` to update it", name);
+                    process::exit(1);
+                }
+            }
+            if let Some(branch) = branch {
+                ws.path = git::worktree(&ws.path, branch).unwrap_or_else(|| {
+                    let path = ws.worktree_path(branch);
+                    eprintln!(
+                        "Creating a worktree for '{}' in {}",
+                        branch,
+                        path.tilde_format()
+                    );
+                    if !git::add_worktree(&ws.path, &path, branch) {
+                        error!("Could not create a worktree for '{}'", branch);
+                        process::exit(1);
+                    }
+                    path
+                });
             }
             let dir_only = matches.is_present("directory");
-            ws.open(dir_only);
+            if !ws.open(matches.value_of("NAME").unwrap(), dir_only) {
+                process::exit(1);
+            }
         }
 
         ("add", Some(matches)) => {
             let name = matches.value_of("NAME").unwrap().to_string();
+            exit_if_reserved(&name);
             if Workspace::exists(&name) {
                 error!("A workspace called '{}' already exists", name);
                 process::exit(1);
@@ -96,9 +139,17 @@ fn main() {
             }
 
             let ws = Workspace {
+                repo: git::remote(&path),
+                worktrees: None,
+                terminal: None,
+                hooks: workspace::Hooks::default(),
+                on_enter: Default::default(),
+                tasks: Default::default(),
+                watch: Default::default(),
                 path,
                 commands: workspace::Commands::default(),
                 tabs: Vec::default(),
+                browser: None,
             };
             ws.write(&name);
             Workspace::edit(&name);
@@ -106,8 +157,8 @@ fn main() {
         }
 
         ("edit", Some(matches)) => {
-            let name = matches.value_of("NAME").unwrap();
-            if !Workspace::exists(&name) {
+            let name = &name_or_current(matches);
+            if !Workspace::exists(name) {
                 error!("A workspace called '{}' does not exist", name);
                 process::exit(1);
             }
@@ -117,27 +168,356 @@ fn main() {
         ("rename", Some(matches)) => {
             let old_name = matches.value_of("OLD_NAME").unwrap();
             let new_name = matches.value_of("NEW_NAME").unwrap();
-            if !Workspace::exists(&old_name) {
+            exit_if_reserved(new_name);
+            if !Workspace::exists(old_name) {
                 error!("A workspace called '{}' does not exist", old_name);
                 process::exit(1);
             }
-            if Workspace::exists(&new_name) {
+            if Workspace::exists(new_name) {
                 error!(
                     "Cannot rename to '{}' because a workspace with that name already exists",
                     new_name
                 );
                 process::exit(1)
             }
-            std::fs::rename(
-                Workspace::file_path(old_name),
-                Workspace::file_path(new_name),
-            )
-            .unwrap_or_exit("Could not rename config file");
+            // Keep the workspace in its folder
+            let old_path = Workspace::file_path(old_name);
+            let mut new_path = old_path.with_file_name(new_name);
+            new_path.set_extension("yaml");
+            std::fs::rename(old_path, new_path).unwrap_or_exit("Could not rename config file");
+        }
+
+        ("move", Some(matches)) => {
+            let name = matches.value_of("NAME").unwrap();
+            let mut ws = get_or_exit(name);
+            let current_dir = env::current_dir().unwrap_or_exit("Could not read current directory");
+            let path = match matches.value_of("NEW_PATH") {
+                Some(path) => current_dir.join(path),
+                None => current_dir,
+            };
+            let path = path.canonicalize().unwrap_or_exit(&format!(
+                "The path '{}' does not exist",
+                path.tilde_format()
+            ));
+            ws.write_path(name, path);
+            println!("Moved workspace '{}' to {}", name, ws.path.tilde_format());
+        }
+
+        ("fix", Some(_)) => {
+            let mut moved: usize = 0;
+            for (name, result) in Workspace::all() {
+                let (name, mut ws) = match (name, result) {
+                    (Some(name), Ok(ws)) => (name, ws),
+                    _ => continue,
+                };
+                skip!(ws.path.exists());
+                moved += 1;
+
+                warn!(
+                    "The path of '{}' does not exist anymore: {}",
+                    name,
+                    ws.path.tilde_format()
+                );
+                let candidate = relocate::candidates(&ws.path, ws.repo.as_deref())
+                    .into_iter()
+                    .find(|candidate| ask!("Move '{}' to {}", name, candidate.tilde_format()));
+                match candidate {
+                    Some(path) => {
+                        ws.write_path(&name, path);
+                        println!("Moved workspace '{}' to {}", name, ws.path.tilde_format());
+                    }
+                    None => {
+                        indent_warn!("Could not find where it was moved");
+                        indent_warn!("run `ws move {} <PATH>` to update it", name);
+                    }
+                }
+            }
+            if moved == 0 {
+                println!("All workspace paths exist");
+            }
+        }
+
+        ("export", Some(matches)) => {
+            let mut bundle = bundle::Bundle::new();
+            match matches.values_of("NAMES") {
+                Some(names) => {
+                    for name in names {
+                        bundle.insert(name.to_string(), get_or_exit(name));
+                    }
+                }
+                None => {
+                    for (name, result) in Workspace::all() {
+                        match (name, result) {
+                            (Some(name), Ok(ws)) => {
+                                bundle.insert(name, ws);
+                            }
+                            (_, Err(error)) => {
+                                warn!("Skipping {}", error.path().tilde_format());
+                                indent_warn!("{}", error);
+                            }
+                            (None, Ok(_)) => {}
+                        }
+                    }
+                }
+            }
+
+            let count = bundle.len();
+            let serialized = bundle::write(&bundle);
+            match matches.value_of("output") {
+                Some(output) => {
+                    fs::write(output, serialized)
+                        .unwrap_or_exit(&format!("Could not write bundle to {}", output));
+                    println!("Exported {} workspace(s) to {}", count, output);
+                }
+                None => print!("{}", serialized),
+            }
+        }
+
+        ("import", Some(matches)) => {
+            let file = matches.value_of("FILE").unwrap();
+            let bundle = bundle::read(path::Path::new(file)).unwrap_or_else(|cause| {
+                error!("Could not read bundle from {}", file);
+                indent_error!("{}", cause);
+                process::exit(1)
+            });
+            let conflict = match matches.value_of("conflict") {
+                Some("rename") => bundle::Conflict::Rename,
+                Some("overwrite") => bundle::Conflict::Overwrite,
+                _ => bundle::Conflict::Skip,
+            };
+
+            // Decide on a name for every workspace and preview it
+            let mut taken: Vec<String> = bundle.keys().cloned().collect();
+            let mut imports: Vec<(String, Workspace)> = Vec::new();
+            for (name, ws) in bundle {
+                let path = ws.path.tilde_format().bright_black();
+                if !Workspace::exists(&name) && !Workspace::is_reserved(&name) {
+                    println!("{}       {} {}", "add".green().bold(), name, path);
+                    imports.push((name, ws));
+                    continue;
+                }
+                match conflict {
+                    bundle::Conflict::Overwrite if !Workspace::is_reserved(&name) => {
+                        println!("{} {} {}", "overwrite".red().bold(), name, path);
+                        imports.push((name, ws));
+                    }
+                    bundle::Conflict::Rename => {
+                        let new_name = bundle::unique_name(&name, &taken);
+                        println!(
+                            "{}    {} as {} {}",
+                            "rename".yellow().bold(),
+                            name,
+                            new_name,
+                            path
+                        );
+                        taken.push(new_name.clone());
+                        imports.push((new_name, ws));
+                    }
+                    _ if Workspace::is_reserved(&name) => {
+                        println!("{}      {} is a reserved name", "skip".bold(), name)
+                    }
+                    _ => println!("{}      {} already exists", "skip".bold(), name),
+                }
+            }
+
+            if imports.is_empty() {
+                println!("Nothing to import");
+                return;
+            }
+            if !matches.is_present("yes") {
+                confirm!("Import {} workspace(s)", imports.len());
+            }
+            for (name, ws) in &imports {
+                ws.write(name);
+            }
+            println!("Imported {} workspace(s)", imports.len());
+        }
+
+        ("close", Some(matches)) => {
+            let full_name: &str = &name_or_current(matches);
+            let (name, branch) = Workspace::split_branch(full_name);
+            let mut ws = get_or_exit(name);
+            if let Some(path) = branch.and_then(|branch| git::worktree(&ws.path, branch)) {
+                ws.path = path;
+            }
+            if ws.close(full_name) {
+                if env::var("WORKSPACE_NAME").is_ok_and(|name| name == full_name) {
+                    set_env!("WORKSPACE_NAME", "");
+                    set_env!("WORKSPACE_PATH", "");
+                }
+                println!("Closed workspace '{}'", full_name);
+            } else {
+                process::exit(1);
+            }
+        }
+
+        ("status", Some(matches)) => {
+            let name: &str = &name_or_current(matches);
+            // Worktrees track their processes under the full name
+            get_or_exit(Workspace::split_branch(name).0);
+            let processes = service::tracked(name);
+            if processes.is_empty() {
+                eprintln!("No background commands are running in '{}'", name);
+                return;
+            }
+            for process in processes {
+                let state = if service::is_running(&process) {
+                    "running".green()
+                } else if process.service {
+                    "failed ".red()
+                } else {
+                    "exited ".bright_black()
+                };
+                println!(
+                    "{}  {}  {}",
+                    state,
+                    process.label,
+                    format!("pid {}", process.pid).bright_black()
+                );
+            }
+        }
+
+        ("run", Some(matches)) => {
+            // NAME is the task if only one of them is given
+            let (name, task) = match matches.value_of("TASK") {
+                Some(task) => (Some(matches.value_of("NAME").unwrap()), task),
+                None => (None, matches.value_of("NAME").unwrap()),
+            };
+            let (name, ws) = match name {
+                Some(name) => (name.to_string(), get_or_exit(name)),
+                None => Workspace::current().unwrap_or_exit(
+                    "The current directory is not in a workspace, please specify its name",
+                ),
+            };
+            let command = match ws.tasks.get(task) {
+                Some(command) => command,
+                None => {
+                    error!("The workspace '{}' has no task called '{}'", name, task);
+                    if !ws.tasks.is_empty() {
+                        let tasks: Vec<&str> = ws.tasks.keys().map(String::as_str).collect();
+                        indent_error!("expected one of {}", tasks.join(", "));
+                    }
+                    process::exit(1);
+                }
+            };
+
+            let mut line = command.clone();
+            for arg in matches.values_of("ARGS").into_iter().flatten() {
+                line.push(' ');
+                line.push_str(&workspace::quote(arg));
+            }
+            let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+            // The shell wrapper reads stdout, so the task uses the terminal directly
+            let status = process::Command::new(shell)
+                .arg("-c")
+                .arg(&line)
+                .current_dir(&ws.path)
+                .stdin(editor::tty())
+                .stdout(editor::tty())
+                .status()
+                .unwrap_or_exit(&format!("Could not run task '{}'", task));
+            process::exit(status.code().unwrap_or(1));
+        }
+
+        ("watch", Some(matches)) => {
+            let (name, branch) = Workspace::split_branch(matches.value_of("NAME").unwrap());
+            let mut ws = get_or_exit(name);
+            if ws.watch.is_empty() {
+                error!("The workspace '{}' does not watch any files", name);
+                indent_error!(
+                    "add glob patterns and commands to `watch` with `ws edit {}`",
+                    name
+                );
+                process::exit(1);
+            }
+            if let Some(path) = branch.and_then(|branch| git::worktree(&ws.path, branch)) {
+                ws.path = path;
+            }
+            watch::watch(&ws);
+        }
+
+        ("current", Some(_)) => {
+            let (name, _) =
+                Workspace::current().unwrap_or_exit("The current directory is not in a workspace");
+            println!("{}", name);
+        }
+
+        ("prompt", Some(_)) => {
+            // Prompts are shown after every command, so the workspace files are only read if
+            // the shell wrapper did not set the workspace
+            let name = match (env::var("WORKSPACE_NAME"), env::var_os("WORKSPACE_PATH")) {
+                (Ok(name), Some(path))
+                    if !name.is_empty()
+                        && env::current_dir().is_ok_and(|dir| dir.starts_with(&path)) =>
+                {
+                    Some(name)
+                }
+                _ => Workspace::current().map(|(name, _)| name),
+            };
+            let name = match name {
+                Some(name) => name,
+                None => return,
+            };
+
+            let mut counts: Vec<String> = Vec::new();
+            let (running, stopped): (Vec<_>, Vec<_>) = service::tracked(&name)
+                .into_iter()
+                .partition(service::is_running);
+            if !running.is_empty() {
+                counts.push(format!("{} running", running.len()));
+            }
+            let failed = stopped.iter().filter(|process| process.service).count();
+            if failed > 0 {
+                counts.push(format!("{} failed", failed));
+            }
+            if counts.is_empty() {
+                println!("{}", name);
+            } else {
+                println!("{} [{}]", name, counts.join(", "));
+            }
+        }
+
+        ("enter", Some(_)) => {
+            // Set by this command to apply `on_enter` only once per workspace
+            let entered = env::var("WORKSPACE_ENTERED").unwrap_or_default();
+            let current = Workspace::current();
+            let name = current.as_ref().map_or("", |(name, _)| name.as_str());
+            if name == entered {
+                return;
+            }
+            // Set first, because commands that change the directory run the hook again
+            set_env!("WORKSPACE_ENTERED", name);
+            // Workspaces from `search_paths` can be written by others, so running their commands
+            // on `cd` would be unsafe
+            if !entered.is_empty() && Workspace::is_own(&entered) {
+                if let Some(Ok(ws)) = Workspace::get(&entered) {
+                    ws.leave();
+                }
+            }
+            if let Some((name, ws)) = &current {
+                if Workspace::is_own(name) {
+                    ws.enter();
+                } else if ws.on_enter != OnEnter::default() {
+                    warn!(
+                        "Not applying on_enter of '{}', because it is not your own",
+                        name
+                    );
+                    indent_warn!("only workspaces in `directory` apply it automatically");
+                }
+            }
+        }
+
+        ("supervise", Some(matches)) => {
+            service::supervise(
+                matches.value_of("SHELL").unwrap(),
+                matches.value_of("COMMAND").unwrap(),
+                matches.is_present("always"),
+            );
         }
 
         ("delete", Some(matches)) => {
             let name: &str = matches.value_of("NAME").unwrap();
-            if !Workspace::file_path(name).exists() {
+            if !Workspace::exists(name) {
                 error!("A workspace called '{}' does not exist", name);
                 process::exit(1);
             }
@@ -150,20 +530,63 @@ fn main() {
             println!("Deleted workspace '{}'", name);
         }
 
-        ("list", Some(_)) => {
+        ("list", Some(matches)) => {
             let all = Workspace::all();
             if all.is_empty() {
                 eprintln!("No workspaces found.\nRun `ws add <NAME>` to create one.");
                 return;
             }
 
+            let sort = match matches.value_of("sort") {
+                Some("path") => config::Sort::Path,
+                Some(_) => config::Sort::Name,
+                None => Config::get().list.sort,
+            };
+            let mut all = all;
+            match sort {
+                config::Sort::Name => all.sort_by(|(a, _), (b, _)| a.cmp(b)),
+                config::Sort::Path => {
+                    all.sort_by_key(|(_, result)| result.as_ref().ok().map(|ws| ws.path.clone()))
+                }
+            }
+
+            let git = matches.is_present("git") || Config::get().list.git;
+            let mut statuses: Vec<Option<git::Status>> = Vec::new();
+            if git {
+                // Every status runs git, so they are computed in parallel
+                thread::scope(|scope| {
+                    let handles: Vec<_> = all
+                        .iter()
+                        .map(|(_, result)| {
+                            scope.spawn(move || {
+                                let ws = result.as_ref().ok()?;
+                                git::status(&ws.path)
+                            })
+                        })
+                        .collect();
+                    for handle in handles {
+                        statuses.push(handle.join().unwrap_or(None));
+                    }
+                });
+            }
+
             use term_grid::{Direction, Filling, Grid, GridOptions};
             let mut grid = Grid::new(GridOptions {
                 filling: Filling::Spaces(2),
                 direction: Direction::LeftToRight,
             });
 
-            for (name, result) in all {
+            // Only show where workspaces are stored if there are several folders
+            let folders = Workspace::folder_paths().len() > 1;
+
+            for (index, (name, result)) in all.into_iter().enumerate() {
+                let folder = match (&name, &result) {
+                    (_, Err(error)) => error.path().parent().map(path::Path::to_path_buf),
+                    (Some(name), Ok(_)) => Workspace::file_path(name)
+                        .parent()
+                        .map(path::Path::to_path_buf),
+                    (None, Ok(_)) => None,
+                };
                 let path: String;
                 let mut moved = String::new();
                 match result {
@@ -182,20 +605,139 @@ fn main() {
 
                 grid.add(name.into());
                 grid.add(path.into());
+                if folders {
+                    let folder = folder.map_or_else(String::new, |folder| folder.tilde_format());
+                    grid.add(folder.bright_black().to_string().into());
+                }
+                if git {
+                    let (branch, state, ahead_behind) = match &statuses[index] {
+                        Some(status) => (
+                            status
+                                .branch
+                                .clone()
+                                .unwrap_or_else(|| "(detached)".to_string()),
+                            if status.dirty {
+                                "dirty".yellow().to_string()
+                            } else {
+                                "clean".green().to_string()
+                            },
+                            match status.ahead_behind {
+                                Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+                                None => String::new(),
+                            },
+                        ),
+                        None => Default::default(),
+                    };
+                    grid.add(branch.into());
+                    grid.add(state.into());
+                    grid.add(ahead_behind.into());
+                }
                 grid.add(moved.into());
             }
-            print!("{}", grid.fit_into_columns(3));
+            let columns = 3 + if folders { 1 } else { 0 } + if git { 3 } else { 0 };
+            print!("{}", grid.fit_into_columns(columns));
+        }
+
+        ("check", Some(_)) => {
+            let all = Workspace::all();
+            let mut problems: usize = 0;
+
+            let mut paths: Vec<(String, path::PathBuf)> = Vec::new();
+            for (name, result) in all {
+                let name = match name {
+                    Some(name) => name,
+                    None => {
+                        error!("Found a workspace with an invalid UTF-8 name");
+                        problems += 1;
+                        continue;
+                    }
+                };
+                let ws = match result {
+                    Ok(ws) => ws,
+                    Err(error) => {
+                        error.report();
+                        problems += 1;
+                        continue;
+                    }
+                };
+
+                for problem in check::check(&ws) {
+                    error!("{}: {}", name, problem);
+                    problems += 1;
+                }
+
+                if let Some((other, _)) = paths.iter().find(|(_, path)| path == &ws.path) {
+                    error!("{}: points to the same path as '{}'", name, other);
+                    problems += 1;
+                }
+                paths.push((name, ws.path));
+            }
+
+            if problems > 0 {
+                eprintln!("Found {} problem(s)", problems);
+                process::exit(1);
+            }
+            println!("No problems found in {} workspace(s)", paths.len());
         }
 
+        ("config", Some(matches)) => match matches.subcommand() {
+            ("get", Some(matches)) => {
+                let key = matches.value_of("KEY").unwrap();
+                exit_if_unknown_key(key);
+                match Config::get().value(key) {
+                    Some(value) => {
+                        let serialized = serde_yaml::to_string(&value).unwrap();
+                        println!("{}", serialized.trim_start_matches("---\n").trim_end());
+                    }
+                    None => eprintln!("'{}' is not set, so its default is used", key),
+                }
+            }
+            ("set", Some(matches)) => {
+                let key = matches.value_of("KEY").unwrap();
+                let value = matches.value_of("VALUE").unwrap();
+                exit_if_unknown_key(key);
+                Config::get()
+                    .with(key, value)
+                    .unwrap_or_exit(&format!("Invalid value for {}: {}", key, value))
+                    .write();
+            }
+            ("edit", Some(_)) => Config::edit(),
+            _ => {}
+        },
+
         ("shell", Some(matches)) => {
+            let flag = |name| {
+                matches
+                    .subcommand()
+                    .1
+                    .is_some_and(|matches| matches.is_present(name))
+            };
+            // The hook runs `ws enter`, which sets variables like the prompt wrappers
+            let on_cd = flag("on-cd");
+            let prompt = flag("prompt") || on_cd;
+            let wrapper = |plain, with_prompt| if prompt { with_prompt } else { plain };
+
             if matches.subcommand_matches("bash").is_some() {
-                println!("{}", shell::BASH);
+                // Separated by a semicolon, because `eval $(workspace shell bash)` joins the lines
+                if on_cd {
+                    println!("{};\n{}", shell::BASH_PROMPT, shell::BASH_ON_CD);
+                } else {
+                    println!("{}", wrapper(shell::BASH, shell::BASH_PROMPT));
+                }
+            } else if matches.subcommand_matches("zsh").is_some() {
+                println!("{}", wrapper(shell::BASH, shell::BASH_PROMPT));
+                if on_cd {
+                    println!("{}", shell::ZSH_ON_CD);
+                }
             } else if matches.subcommand_matches("fish").is_some() {
-                println!("{}", shell::FISH);
+                println!("{}", wrapper(shell::FISH, shell::FISH_PROMPT));
+                if on_cd {
+                    println!("{}", shell::FISH_ON_CD);
+                }
             } else if matches.subcommand_matches("powershell").is_some() {
-                println!("{}", shell::POWERSHELL)
+                println!("{}", wrapper(shell::POWERSHELL, shell::POWERSHELL_PROMPT))
             } else if let Some(matches) = matches.subcommand_matches("cmd") {
-                let path: path::PathBuf = path_to_binary_or_arg(&matches);
+                let path: path::PathBuf = path_to_binary_or_arg(matches);
                 let mut file: fs::File = fs::OpenOptions::new()
                     .read(false)
                     .write(true)
@@ -208,7 +750,12 @@ fn main() {
                         path.tilde_format()
                     ));
 
-                file.write_fmt(format_args!("{}", shell::CMD))
+                let cmd = if prompt {
+                    shell::CMD_PROMPT
+                } else {
+                    shell::CMD
+                };
+                file.write_fmt(format_args!("{}", cmd))
                     .unwrap_or_exit("Could not write to batch file");
 
                 println!("Wrote {}", path.tilde_format());
@@ -219,16 +766,54 @@ fn main() {
     }
 }
 
+/// Returns the NAME argument, or the workspace containing the current directory
+fn name_or_current(matches: &ArgMatches) -> String {
+    match matches.value_of("NAME") {
+        Some(name) => name.to_string(),
+        None => {
+            let (name, _) = Workspace::current().unwrap_or_exit(
+                "The current directory is not in a workspace, please specify its name",
+            );
+            name
+        }
+    }
+}
+
+fn get_or_exit(name: &str) -> Workspace {
+    Workspace::get(name)
+        .unwrap_or_exit(&format!("A workspace called '{}' does not exist", name))
+        .unwrap_or_else(|error| {
+            error.report();
+            process::exit(1)
+        })
+}
+
+fn exit_if_unknown_key(key: &str) {
+    if !config::KEYS.contains(&key) {
+        error!("Unknown setting '{}'", key);
+        indent_error!("expected one of {}", config::KEYS.join(", "));
+        process::exit(1);
+    }
+}
+
+/// Exits if a workspace would be stored in the configuration file
+fn exit_if_reserved(name: &str) {
+    if Workspace::is_reserved(name) {
+        error!("The name '{}' is reserved for the configuration", name);
+        process::exit(1);
+    }
+}
+
 fn path_to_binary_or_arg(matches: &ArgMatches) -> path::PathBuf {
     if let Some(path) = matches.value_of("PATH") {
-        return path::Path::new(path)
+        path::Path::new(path)
             .with_file_name("ws")
             .with_extension("bat")
-            .to_path_buf();
+            .to_path_buf()
     } else {
         let mut path = env::current_exe().unwrap_or_exit("Could not determine path to binary");
         path.set_file_name("ws");
         path.set_extension("bat");
-        return path;
+        path
     }
 }
@@ -0,0 +1,74 @@
+use crate::git;
+use crate::workspace;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many levels below the home directory are searched
+const DEPTH: usize = 4;
+
+/// Directories that are skipped, because they are large and never contain projects
+pub(crate) const SKIPPED: &[&str] = &["node_modules", "target", "vendor", "venv"];
+
+/// Returns locations where a project that used to be at `path` was likely moved to
+pub fn candidates(path: &Path, repo: Option<&str>) -> Vec<PathBuf> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return Vec::new(),
+    };
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    // Siblings with a similar name, in case the project was renamed
+    if let Some(parent) = path.parent() {
+        for dir in subdirectories(parent) {
+            let sibling = dir.file_name().unwrap().to_string_lossy().to_string();
+            if workspace::distance(&name, &sibling) <= std::cmp::max(2, name.len() / 3) {
+                candidates.push(dir);
+            }
+        }
+    }
+
+    // Directories under home with the same name or a matching git remote
+    if let Some(home) = dirs::home_dir() {
+        search(&home, &name, repo, DEPTH, &mut candidates);
+    }
+
+    candidates
+}
+
+fn search(dir: &Path, name: &str, repo: Option<&str>, depth: usize, candidates: &mut Vec<PathBuf>) {
+    for dir in subdirectories(dir) {
+        let dir_name = dir.file_name().unwrap().to_string_lossy().to_string();
+        if dir_name.starts_with('.') || SKIPPED.contains(&dir_name.as_str()) {
+            continue;
+        }
+
+        let matches = dir_name == name
+            || (dir.join(".git").exists()
+                && git::remote(&dir).is_some_and(|url| match repo {
+                    Some(repo) => url == repo,
+                    None => git::repository_name(&url) == name,
+                }));
+        if matches && !candidates.contains(&dir) {
+            candidates.push(dir.clone());
+        }
+
+        if depth > 1 {
+            search(&dir, name, repo, depth - 1, candidates);
+        }
+    }
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    dirs
+}
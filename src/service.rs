@@ -0,0 +1,385 @@
+use crate::workspace::Command;
+use crate::VERBOSE;
+
+use std::fs;
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+
+/// How often readiness probes are checked
+const INTERVAL: Duration = Duration::from_millis(200);
+/// How long a supervisor waits before restarting a command
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Orders background commands so that each one comes after its dependencies, keeping the
+/// original order otherwise. Returns an error for unknown dependencies and cycles.
+pub fn order(commands: &[Command]) -> Result<Vec<&Command>, String> {
+    for command in commands {
+        for dependency in command.depends_on() {
+            if !commands
+                .iter()
+                .any(|other| other.name() == Some(dependency))
+            {
+                return Err(format!(
+                    "`{}` depends on the unknown command '{}'",
+                    command.label(),
+                    dependency
+                ));
+            }
+        }
+    }
+
+    let mut placed = vec![false; commands.len()];
+    let mut ordered: Vec<&Command> = Vec::new();
+    while ordered.len() < commands.len() {
+        let next = (0..commands.len()).find(|&index| {
+            !placed[index]
+                && commands[index].depends_on().iter().all(|dependency| {
+                    ordered
+                        .iter()
+                        .any(|other| other.name() == Some(dependency.as_str()))
+                })
+        });
+        match next {
+            Some(index) => {
+                placed[index] = true;
+                ordered.push(&commands[index]);
+            }
+            None => return Err("The dependencies of background commands form a cycle".into()),
+        }
+    }
+    Ok(ordered)
+}
+
+/// Waits until the readiness probes of a command succeed, or returns false after its timeout
+pub fn wait(command: &Command, dir: &Path, log: &Path) -> bool {
+    let service = match command.details() {
+        Some(details) => details,
+        None => return true,
+    };
+    let timeout = Duration::from_secs(service.timeout);
+    let start = Instant::now();
+
+    loop {
+        let port = service.wait_for_port.is_none_or(|port| {
+            let address = SocketAddr::from(([127, 0, 0, 1], port));
+            TcpStream::connect_timeout(&address, INTERVAL).is_ok()
+        });
+        let file = service
+            .wait_for_file
+            .as_ref()
+            .is_none_or(|file| dir.join(file).exists());
+        let log = service.wait_for_log.as_ref().is_none_or(|text| {
+            fs::read_to_string(log).is_ok_and(|content| content.contains(text.as_str()))
+        });
+
+        if port && file && log {
+            return true;
+        }
+        if start.elapsed() > timeout {
+            return false;
+        }
+        thread::sleep(INTERVAL);
+    }
+}
+
+/// Returns the file that the output of a background command is written to
+pub fn log_path(workspace: &str, command: &str) -> Option<PathBuf> {
+    let mut path = dirs::cache_dir()?;
+    path.push("workspace");
+    path.push("logs");
+    fs::create_dir_all(&path).ok()?;
+    path.push(format!("{}-{}.log", workspace, command).replace(['/', '\\'], "-"));
+    Some(path)
+}
+
+/// Returns whether nothing listens on a port yet
+pub fn is_port_free(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// Waits a few seconds for a port to become free, e.g. after closing the workspace using it
+pub fn wait_for_free_port(port: u16) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if is_port_free(port) {
+            return true;
+        }
+        thread::sleep(INTERVAL);
+    }
+    false
+}
+
+/// Returns the workspace whose tracked processes listen on a port, found with `lsof` and `ps`
+pub fn port_owner(port: u16) -> Option<String> {
+    let result = process::Command::new("lsof")
+        .arg("-t")
+        .arg(format!("-iTCP:{}", port))
+        .arg("-sTCP:LISTEN")
+        .stderr(Stdio::null())
+        .output();
+    let output = match result {
+        Ok(output) => output,
+        Err(error) => {
+            log!(
+                "Could not run lsof to find out who uses port {}: {}",
+                port,
+                error
+            );
+            return None;
+        }
+    };
+    let groups: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|pid| {
+            let output = process::Command::new("ps")
+                .args(["-o", "pgid=", "-p", pid.trim()])
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        })
+        .collect();
+
+    let entries = fs::read_dir(processes_folder()?).ok()?;
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .find(|workspace| {
+            tracked(workspace)
+                .iter()
+                .any(|process| groups.contains(&process.pid) && is_running(process))
+        })
+}
+
+/// Returns the folder with a file of tracked processes for each workspace
+fn processes_folder() -> Option<PathBuf> {
+    let mut path = dirs::cache_dir()?;
+    path.push("workspace");
+    path.push("processes");
+    fs::create_dir_all(&path).ok()?;
+    Some(path)
+}
+
+fn processes_path(workspace: &str) -> Option<PathBuf> {
+    Some(processes_folder()?.join(workspace.replace(['/', '\\'], "-")))
+}
+
+/// A background process started by `ws open`
+pub struct Process {
+    /// Also the id of its process group
+    pub pid: u32,
+    /// When the process started according to `ps`, to recognize a reused pid
+    pub started: String,
+    /// Whether the process should keep running, so that it failed if it exited
+    pub service: bool,
+    pub label: String,
+}
+
+impl Process {
+    pub fn new(pid: u32, service: bool, label: &str) -> Process {
+        Process {
+            pid,
+            started: start_time(pid).unwrap_or_default(),
+            service,
+            label: label.to_string(),
+        }
+    }
+
+    /// Returns the line that stores the process in its workspace's file
+    fn line(&self) -> String {
+        let kind = if self.service { "service" } else { "command" };
+        format!("{}\t{}\t{}\t{}\n", self.pid, kind, self.started, self.label)
+    }
+}
+
+/// Returns the start time of a process, or `None` if it is gone
+fn start_time(pid: u32) -> Option<String> {
+    let output = process::Command::new("ps")
+        .args(["-o", "lstart=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && !started.is_empty() {
+        Some(started)
+    } else {
+        None
+    }
+}
+
+/// Remembers a background process, so that `ws status` can show it and `ws close` stop it
+pub fn track(workspace: &str, process: &Process) {
+    let path = match processes_path(workspace) {
+        Some(path) => path,
+        None => return,
+    };
+    let result = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(process.line().as_bytes()));
+    if let Err(error) = result {
+        warn!("Could not track the process of `{}`", process.label);
+        log!("{}", error);
+    }
+}
+
+/// Returns the tracked processes of a workspace
+pub fn tracked(workspace: &str) -> Vec<Process> {
+    let content = processes_path(workspace)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, '\t');
+            Some(Process {
+                pid: parts.next()?.parse().ok()?,
+                service: parts.next()? == "service",
+                started: parts.next()?.to_string(),
+                label: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Forgets the tracked processes of a workspace that are no longer running
+pub fn prune(workspace: &str) {
+    let path = match processes_path(workspace) {
+        Some(path) if path.exists() => path,
+        _ => return,
+    };
+    let running: Vec<Process> = tracked(workspace).into_iter().filter(is_running).collect();
+    if running.is_empty() {
+        fs::remove_file(path).ok();
+        return;
+    }
+    let content: String = running.iter().map(Process::line).collect();
+    if let Err(error) = fs::write(path, content) {
+        log!(
+            "Could not forget the stopped processes of '{}': {}",
+            workspace,
+            error
+        );
+    }
+}
+
+/// Returns whether a tracked process or any process in its group is still running. While the
+/// group exists its id is not reused, and a process with the same pid must have the same start
+/// time, otherwise the pid belongs to an unrelated process now.
+pub fn is_running(process: &Process) -> bool {
+    if !kill(process.pid, "-0") {
+        return false;
+    }
+    match start_time(process.pid) {
+        Some(started) => process.started.is_empty() || started == process.started,
+        // Only the group leader exited
+        None => true,
+    }
+}
+
+/// Stops the tracked processes of a workspace, returns how many were running
+pub fn stop(workspace: &str) -> usize {
+    let stopped = tracked(workspace)
+        .into_iter()
+        .filter(|process| is_running(process) && kill(process.pid, "-TERM"))
+        .count();
+    if let Some(path) = processes_path(workspace) {
+        fs::remove_file(path).ok();
+    }
+    stopped
+}
+
+/// Sends a signal to the process group of a tracked process, which also reaches its children
+fn kill(pid: u32, signal: &str) -> bool {
+    process::Command::new("kill")
+        .arg(signal)
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Runs a command with `<shell> -c` and restarts it when it fails, or also when it succeeds if
+/// `always` is set. Restarts are delayed more and more while the command keeps crashing.
+pub fn supervise(shell: &str, command: &str, always: bool) -> ! {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let start = Instant::now();
+        let status = process::Command::new(shell).arg("-c").arg(command).status();
+        let message = match status {
+            Ok(status) if status.success() && !always => process::exit(0),
+            Ok(status) if status.success() => format!("`{}` exited", command),
+            Ok(status) => format!("`{}` failed with {}", command, status),
+            Err(error) => format!("could not run `{}`: {}", command, error),
+        };
+
+        // A command that ran for a while is restarted quickly again
+        if start.elapsed() > MAX_BACKOFF {
+            backoff = MIN_BACKOFF;
+        }
+        eprintln!(
+            "[workspace] {}, restarting in {}s",
+            message,
+            backoff.as_secs()
+        );
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(yaml: &str) -> Vec<Command> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn runs(ordered: Vec<&Command>) -> Vec<&str> {
+        ordered.into_iter().map(Command::run).collect()
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let commands = commands(
+            "- {run: web, name: web, depends_on: [db, cache]}\n\
+             - plain\n\
+             - {run: db, name: db}\n\
+             - {run: cache, name: cache, depends_on: [db]}",
+        );
+        let ordered = order(&commands).unwrap();
+        assert_eq!(runs(ordered), ["plain", "db", "cache", "web"]);
+    }
+
+    #[test]
+    fn unknown_dependency() {
+        let commands = commands("- {run: web, depends_on: [db]}");
+        let error = order(&commands).unwrap_err();
+        assert!(error.contains("unknown command 'db'"), "{}", error);
+    }
+
+    #[test]
+    fn cycle() {
+        let commands = commands(
+            "- {run: a, name: a, depends_on: [b]}\n\
+             - {run: b, name: b, depends_on: [a]}",
+        );
+        let error = order(&commands).unwrap_err();
+        assert!(error.contains("cycle"), "{}", error);
+    }
+
+    #[test]
+    fn duplicates() {
+        let commands = commands("- sleep 5\n- sleep 5");
+        let ordered = order(&commands).unwrap();
+        assert_eq!(runs(ordered), ["sleep 5", "sleep 5"]);
+    }
+}
@@ -42,3 +42,87 @@ GOTO :EOF
         ECHO %~1
     )
     GOTO :EOF"#;
+
+/// Like `BASH`, but also sets environment variables like $WORKSPACE_NAME
+pub const BASH_PROMPT: &str = r#"function ws {
+    while read -r line; do
+        if [[ "$line" == RUN\>* ]]; then
+            eval "${line:4}" < /dev/tty;
+        elif [[ "$line" == SET\>* ]]; then
+            export "${line:4}";
+        elif [[ "$line" == UNSET\>* ]]; then
+            unset "${line:6}";
+        else
+            echo "$line";
+        fi;
+    done < <( workspace --from-shell-wrapper --set-env "$@" );
+}"#;
+
+pub const FISH_PROMPT: &str = r#"function ws
+    workspace --from-shell-wrapper --set-env $argv | while read line
+        if set command (string replace -r "^RUN>" "" $line)
+            eval $command < /dev/tty
+        else if set variable (string replace -r "^SET>" "" $line)
+            set -gx (string split -m 1 "=" $variable)
+        else if set variable (string replace -r "^UNSET>" "" $line)
+            set -e $variable
+        else
+            echo $line
+        end
+    end
+end"#;
+
+pub const POWERSHELL_PROMPT: &str = r#"function ws {
+    workspace --from-shell-wrapper --set-env $args | % {
+        if ($_ -match "^RUN>") {
+            . ([scriptblock]::Create($_.Substring(4)))
+        } elseif ($_ -match "^SET>") {
+            $name, $value = $_.Substring(4).Split("=", 2)
+            Set-Item "env:$name" $value
+        } elseif ($_ -match "^UNSET>") {
+            Remove-Item "env:$($_.Substring(6))" -ErrorAction SilentlyContinue
+        } else {
+            Write-Output $_
+        }
+    }
+}"#;
+
+pub const CMD_PROMPT: &str = r#"@ECHO off
+FOR /F "tokens=* delims=" %%G IN ('workspace --from-shell-wrapper --set-env %*') DO (
+    CALL :subroutine "%%G"
+)
+GOTO :EOF
+
+:subroutine
+    SET "temp=%~1"
+    IF "%temp:~0,4%" == "RUN>" (
+        CALL %temp:~4%
+    ) ELSE IF "%temp:~0,4%" == "SET>" (
+        SET "%temp:~4%"
+    ) ELSE IF "%temp:~0,6%" == "UNSET>" (
+        SET "%temp:~6%="
+    ) ELSE (
+        ECHO %~1
+    )
+    GOTO :EOF"#;
+
+/// Runs `ws enter` whenever the directory changes, printed after a prompt wrapper
+pub const BASH_ON_CD: &str = r#"function __ws_on_cd {
+    if [[ "$PWD" != "$__WS_LAST_DIR" ]]; then
+        __WS_LAST_DIR="$PWD";
+        ws enter;
+    fi;
+};
+PROMPT_COMMAND="__ws_on_cd${PROMPT_COMMAND:+;$PROMPT_COMMAND}";"#;
+
+pub const ZSH_ON_CD: &str = r#"function __ws_on_cd {
+    ws enter;
+}
+autoload -U add-zsh-hook
+add-zsh-hook chpwd __ws_on_cd
+__ws_on_cd"#;
+
+pub const FISH_ON_CD: &str = r#"function __ws_on_cd --on-variable PWD
+    ws enter
+end
+__ws_on_cd"#;
@@ -0,0 +1,63 @@
+use crate::config::Config;
+use crate::workspace::Terminal;
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Arguments for terminals that need more than the command, separated by whitespace
+const PRESETS: &[(&str, &str)] = &[
+    ("alacritty", "--working-directory {path} -e sh -c {command}"),
+    ("foot", "--working-directory={path} sh -c {command}"),
+    (
+        "gnome-terminal",
+        "--working-directory={path} -- sh -c {command}",
+    ),
+    ("kitty", "--directory {path} sh -c {command}"),
+    ("konsole", "--workdir {path} -e sh -c {command}"),
+    ("urxvt", "-cd {path} -e sh -c {command}"),
+    ("wezterm", "start --cwd {path} -- sh -c {command}"),
+    (
+        "xfce4-terminal",
+        "--working-directory={path} -x sh -c {command}",
+    ),
+    ("xterm", "-e sh -c {command}"),
+];
+
+/// Returns how to run external commands: the workspace's setting, the global setting, or a
+/// template for $TERMINAL
+pub fn resolve(terminal: Option<&Terminal>) -> Option<Terminal> {
+    if let Some(terminal) = terminal.or(Config::get().terminal.as_ref()) {
+        return Some(terminal.clone());
+    }
+    env::var("TERMINAL")
+        .ok()
+        .map(|terminal| Terminal::Template(preset(&terminal)))
+}
+
+/// Returns the template for a terminal, based on the name of its binary
+pub fn preset(terminal: &str) -> Vec<String> {
+    let name = Path::new(terminal)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let args: &str = PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map_or("{command}", |(_, args)| args);
+
+    let mut template = vec![terminal.to_string()];
+    template.extend(args.split_whitespace().map(str::to_string));
+    template
+}
+
+/// Builds the process for a template by replacing `{path}` and `{command}` in its arguments
+pub fn command(template: &[String], path: &Path, command: &str) -> Command {
+    let path = path.display().to_string();
+    let mut args = template
+        .iter()
+        .map(|arg| arg.replace("{path}", &path).replace("{command}", command));
+    let mut process = Command::new(args.next().unwrap_or_default());
+    process.args(args).current_dir(&path);
+    process
+}
@@ -1,20 +1,89 @@
-use std::path::PathBuf;
+use std::env;
+use std::path::{Path, PathBuf};
 
 pub trait Tilde {
     fn tilde_format(&self) -> String;
+    fn tilde_expand(&self) -> PathBuf;
 }
 
-impl Tilde for PathBuf {
+impl Tilde for Path {
     fn tilde_format(&self) -> String {
-        let path = self.display().to_string();
-        let home_dir = match dirs::home_dir() {
-            Some(dir) => dir.display().to_string(),
-            None => String::new(),
+        match dirs::home_dir().and_then(|home| self.strip_prefix(home).ok()) {
+            Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+            Some(relative) => Path::new("~").join(relative).display().to_string(),
+            None => self.display().to_string(),
+        }
+    }
+
+    /// Replaces a leading `~` with the home directory and `$VAR` or `${VAR}` with the value
+    /// of the environment variable, unless it is not set
+    fn tilde_expand(&self) -> PathBuf {
+        let path = match self.to_str() {
+            Some(path) => expand_variables(path),
+            None => return self.to_path_buf(),
+        };
+        let path = PathBuf::from(path);
+        match (path.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(relative), Some(home)) => home.join(relative),
+            _ => path,
+        }
+    }
+}
+
+fn expand_variables(text: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let (name, remainder) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
         };
-        if path.starts_with(home_dir.as_str()) {
-            path.replacen(home_dir.as_str(), "~", 1)
-        } else {
-            path
+
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => {
+                expanded.push('$');
+                expanded.push_str(&rest[..rest.len() - remainder.len()]);
+            }
         }
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables() {
+        env::set_var("WORKSPACE_TEST_VARIABLE", "value");
+        assert_eq!(
+            expand_variables("a/$WORKSPACE_TEST_VARIABLE/b"),
+            "a/value/b"
+        );
+        assert_eq!(expand_variables("${WORKSPACE_TEST_VARIABLE}s"), "values");
+        assert_eq!(
+            expand_variables("$WORKSPACE_TEST_UNSET/b"),
+            "$WORKSPACE_TEST_UNSET/b"
+        );
+        assert_eq!(
+            expand_variables("${WORKSPACE_TEST_UNSET}"),
+            "${WORKSPACE_TEST_UNSET}"
+        );
+        assert_eq!(expand_variables("cost: $"), "cost: $");
+        assert_eq!(expand_variables("${unclosed"), "${unclosed");
     }
 }
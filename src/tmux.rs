@@ -0,0 +1,55 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Runs tmux with `args` and returns whether it succeeded
+fn tmux(args: &[&str], dir: Option<&Path>, command: Option<&str>) -> bool {
+    let mut tmux = Command::new("tmux");
+    tmux.args(args);
+    // The working directory and command must follow the other arguments in this order
+    if let Some(dir) = dir {
+        tmux.arg("-c").arg(dir);
+    }
+    if let Some(command) = command {
+        tmux.arg(command);
+    }
+    tmux.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Returns the session name for a workspace, because tmux doesn't allow `.` and `:` in names
+pub fn session_name(name: &str) -> String {
+    name.replace(['.', ':'], "_")
+}
+
+pub fn has_session(session: &str) -> bool {
+    tmux(&["has-session", "-t", &format!("={}", session)], None, None)
+}
+
+/// Creates a detached session whose first window runs `command`
+pub fn new_session(session: &str, dir: &Path, command: &str) -> bool {
+    tmux(
+        &["new-session", "-d", "-s", session],
+        Some(dir),
+        Some(command),
+    )
+}
+
+pub fn new_window(session: &str, dir: &Path, command: &str) -> bool {
+    let target = format!("={}:", session);
+    tmux(
+        &["new-window", "-d", "-t", &target],
+        Some(dir),
+        Some(command),
+    )
+}
+
+pub fn kill_session(session: &str) -> bool {
+    tmux(
+        &["kill-session", "-t", &format!("={}", session)],
+        None,
+        None,
+    )
+}
@@ -0,0 +1,110 @@
+use crate::relocate::SKIPPED;
+use crate::workspace::Workspace;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use colored::Colorize;
+
+/// How often files are checked for changes
+const INTERVAL: Duration = Duration::from_millis(500);
+
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Runs the `watch` commands of a workspace whenever files matching their patterns change.
+/// Changes are debounced: a command runs once the files stop changing for an interval.
+pub fn watch(ws: &Workspace) -> ! {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let mut snapshots: Vec<Snapshot> = ws
+        .watch
+        .keys()
+        .map(|pattern| snapshot(ws, pattern))
+        .collect();
+
+    loop {
+        thread::sleep(INTERVAL);
+        for ((pattern, command), last) in ws.watch.iter().zip(&mut snapshots) {
+            let mut current = snapshot(ws, pattern);
+            skip!(current == *last);
+            // Waits until the files have settled, e.g. after saving several of them
+            loop {
+                thread::sleep(INTERVAL);
+                let next = snapshot(ws, pattern);
+                if next == current {
+                    break;
+                }
+                current = next;
+            }
+            *last = current;
+
+            eprintln!("{} `{}`", "Running".bold(), command);
+            let status = process::Command::new(&shell)
+                .arg("-c")
+                .arg(command)
+                .current_dir(&ws.path)
+                .status();
+            match status {
+                Ok(status) if status.success() => {}
+                Ok(status) => error!("`{}` failed with {}", command, status),
+                Err(error) => error!("Could not run `{}`: {}", command, error),
+            }
+        }
+    }
+}
+
+/// Returns the modification times of the files matching a pattern, relative to the workspace.
+/// Like in a shell, wildcards don't match hidden files such as `.git`, and directories like
+/// `target` and `node_modules` are skipped unless the pattern names them.
+fn snapshot(ws: &Workspace, pattern: &str) -> Snapshot {
+    let full_pattern = ws.path.join(pattern);
+    let options = glob::MatchOptions {
+        require_literal_leading_dot: true,
+        ..Default::default()
+    };
+    let paths = match glob::glob_with(&full_pattern.to_string_lossy(), options) {
+        Ok(paths) => paths,
+        Err(_) => return Snapshot::new(),
+    };
+    paths
+        .filter_map(Result::ok)
+        .filter(|path| !is_skipped(ws, pattern, path))
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+            (path, modified.ok())
+        })
+        .collect()
+}
+
+fn is_skipped(ws: &Workspace, pattern: &str, path: &Path) -> bool {
+    let relative = path.strip_prefix(&ws.path).unwrap_or(path);
+    let mut dirs = relative.parent().into_iter().flat_map(Path::components);
+    dirs.any(|dir| {
+        let dir = dir.as_os_str().to_string_lossy();
+        SKIPPED.contains(&dir.as_ref()) && !pattern.contains(dir.as_ref())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_directories() {
+        let ws: Workspace = serde_yaml::from_str("path: /code/blog").unwrap();
+        let path = |path: &str| Path::new("/code/blog").join(path);
+        assert!(!is_skipped(&ws, "**/*.rs", &path("src/main.rs")));
+        assert!(!is_skipped(&ws, "**/*.rs", &path("target.rs")));
+        assert!(is_skipped(&ws, "**/*.rs", &path("target/debug/build.rs")));
+        assert!(is_skipped(
+            &ws,
+            "**/*.js",
+            &path("web/node_modules/a/index.js")
+        ));
+        assert!(!is_skipped(&ws, "target/*.log", &path("target/build.log")));
+    }
+}
@@ -1,112 +1,931 @@
+use crate::browser;
+use crate::config::Config;
+use crate::editor;
 use crate::exit::Exit;
+use crate::git;
+use crate::service;
+use crate::terminal;
 use crate::tilde::Tilde;
-use crate::VERBOSE;
+use crate::tmux;
+use crate::{SET_ENV, VERBOSE};
 
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{self, Stdio};
 
 use colored::Colorize;
-use failure::Fail;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize as _, Deserializer, Serializer};
 use serde_derive::{Deserialize, Serialize};
 
-#[serde(deny_unknown_fields)]
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Workspace {
+    /// Absolute once loaded, can start with `~` or contain `$VAR` in the file, and is relative
+    /// to the home directory otherwise
+    #[serde(deserialize_with = "expand_from_home", serialize_with = "contract")]
     pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Like `path`, can start with `~` or contain `$VAR`, and is relative to `path` otherwise
+    #[serde(
+        default,
+        deserialize_with = "expand_option",
+        serialize_with = "contract_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub worktrees: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal: Option<Terminal>,
     #[serde(default, skip_serializing_if = "is_default")]
-    pub tabs: Vec<String>,
+    pub tabs: Vec<Tab>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser: Option<Browser>,
     #[serde(default, skip_serializing_if = "is_default")]
     pub commands: Commands,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub hooks: Hooks,
+    /// Applied whenever the shell enters the workspace, see `workspace shell --on-cd`
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub on_enter: OnEnter,
+    /// Commands that `ws run` runs by name
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub tasks: BTreeMap<String, String>,
+    /// Commands that run when files matching a glob pattern change, see `ws watch`
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub watch: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Commands {
     #[serde(default, skip_serializing_if = "is_default")]
-    pub local: Vec<String>,
+    pub local: Vec<Command>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub external: Vec<Command>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub background: Vec<Command>,
+}
+
+/// Either a plain command or a table with options
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Command {
+    Plain(String),
+    Detailed(Box<Details>),
+}
+
+// Not derived with `untagged`, which would hide the errors of the table, like unknown fields
+impl<'de> serde::Deserialize<'de> for Command {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CommandVisitor;
+
+        impl<'de> Visitor<'de> for CommandVisitor {
+            type Value = Command;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a command or a table with `run`")
+            }
+
+            fn visit_str<E: de::Error>(self, command: &str) -> Result<Command, E> {
+                Ok(Command::Plain(command.to_string()))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Command, M::Error> {
+                Details::deserialize(MapAccessDeserializer::new(map))
+                    .map(|details| Command::Detailed(Box::new(details)))
+            }
+        }
+
+        deserializer.deserialize_any(CommandVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Details {
+    #[serde(alias = "cmd")]
+    pub run: String,
+    /// Shown instead of the command in messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Relative to the workspace path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub env: BTreeMap<String, String>,
+    /// A shell command that must succeed for the command to run
+    #[serde(default, rename = "if", skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Runs the command with `<shell> -c` instead of the current shell or $SHELL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// An operating system like `linux`, `macos` and `windows`, or `unix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<When>,
+
+    // The remaining options only apply to background commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Names of background commands that must be ready before this one starts
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for_port: Option<u16>,
+    /// Relative to the workspace path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for_log: Option<String>,
+    /// Ports the command listens on, which must be free before it starts
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ports: Vec<u16>,
+    /// Whether a supervisor restarts the command when it exits
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub restart: Restart,
+    /// Seconds to wait for the command to be ready
+    #[serde(
+        default = "default_timeout",
+        skip_serializing_if = "is_default_timeout"
+    )]
+    pub timeout: u64,
+}
+
+impl Command {
+    pub fn run(&self) -> &str {
+        match self {
+            Command::Plain(command) => command,
+            Command::Detailed(details) => &details.run,
+        }
+    }
+
+    pub fn details(&self) -> Option<&Details> {
+        match self {
+            Command::Plain(_) => None,
+            Command::Detailed(details) => Some(details),
+        }
+    }
+
+    /// Returns the description, or the command itself if there is none
+    pub fn label(&self) -> &str {
+        self.details()
+            .and_then(|details| details.description.as_deref())
+            .unwrap_or_else(|| self.run())
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.details().and_then(|details| details.name.as_deref())
+    }
+
+    pub fn depends_on(&self) -> &[String] {
+        self.details()
+            .map_or(&[], |details| details.depends_on.as_slice())
+    }
+
+    /// Returns whether the command is meant to keep running, so that it failed if it exited,
+    /// because it is restarted, listens on ports or waits for a port or a log line. Waiting for
+    /// a file doesn't count, because one-off commands like builds produce files.
+    pub fn is_service(&self) -> bool {
+        self.details().is_some_and(|details| {
+            details.restart != Restart::Never
+                || !details.ports.is_empty()
+                || details.wait_for_port.is_some()
+                || details.wait_for_log.is_some()
+        })
+    }
+
+    pub fn env(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.details().into_iter().flat_map(|details| &details.env)
+    }
+
+    /// Returns the directory the command runs in
+    pub fn dir(&self, path: &Path) -> PathBuf {
+        match self.details().and_then(|details| details.cwd.as_ref()) {
+            Some(cwd) => path.join(cwd),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Returns the command as a line for a shell, with its variables and `shell` applied
+    pub fn line(&self) -> String {
+        let details = match self {
+            Command::Plain(command) => return command.clone(),
+            Command::Detailed(details) => details,
+        };
+        let mut line = String::new();
+        for (variable, value) in &details.env {
+            line.push_str(&format!("{}={} ", variable, quote(value)));
+        }
+        match &details.shell {
+            Some(shell) => line.push_str(&format!("{} -c {}", shell, quote(&details.run))),
+            None => line.push_str(&details.run),
+        }
+        line
+    }
+
+    /// Returns whether the command runs on this platform and its `if` condition succeeds
+    pub fn is_enabled(&self, path: &Path) -> bool {
+        let details = match self {
+            Command::Plain(_) => return true,
+            Command::Detailed(details) => details,
+        };
+        if let Some(platform) = &details.platform {
+            if platform != env::consts::OS && platform != env::consts::FAMILY {
+                log!("Skipping `{}` on {}", self.label(), env::consts::OS);
+                return false;
+            }
+        }
+        if let Some(condition) = details.when.as_ref().and_then(When::failed) {
+            log!("Skipping `{}`, because of `{}`", self.label(), condition);
+            return false;
+        }
+        if let Some(condition) = &details.condition {
+            let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+            let status = process::Command::new(shell)
+                .arg("-c")
+                .arg(condition)
+                .current_dir(self.dir(path))
+                .envs(&details.env)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            if !status.is_ok_and(|status| status.success()) {
+                log!(
+                    "Skipping `{}`, because `{}` failed",
+                    self.label(),
+                    condition
+                );
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Restart {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
+
+/// Either a URL or a table with a URL and a condition
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Tab {
+    Url(String),
+    Detailed {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        when: Option<When>,
+    },
+}
+
+/// The table form of `Tab`, which is deserialized on its own to report unknown fields
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TabDetails {
+    url: String,
+    #[serde(default)]
+    when: Option<When>,
+}
+
+impl<'de> serde::Deserialize<'de> for Tab {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TabVisitor;
+
+        impl<'de> Visitor<'de> for TabVisitor {
+            type Value = Tab;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a URL or a table with `url`")
+            }
+
+            fn visit_str<E: de::Error>(self, url: &str) -> Result<Tab, E> {
+                Ok(Tab::Url(url.to_string()))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Tab, M::Error> {
+                TabDetails::deserialize(MapAccessDeserializer::new(map))
+                    .map(|TabDetails { url, when }| Tab::Detailed { url, when })
+            }
+        }
+
+        deserializer.deserialize_any(TabVisitor)
+    }
+}
+
+/// How the tabs of a workspace are opened
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Browser {
+    /// An argument list containing `{url}` and `{profile}`, defaults to the global setting
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
     #[serde(default, skip_serializing_if = "is_default")]
-    pub external: Vec<String>,
+    pub new_window: bool,
+    /// Opens all tabs with one invocation of the browser instead of one each
     #[serde(default, skip_serializing_if = "is_default")]
-    pub background: Vec<String>,
+    pub together: bool,
+}
+
+/// Where a command runs or a tab opens, all of the given conditions must hold
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct When {
+    /// An operating system like `linux`, `macos` and `windows`, or `unix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// A hostname, which can contain wildcards like `work-*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// A variable that must be set, like `CI`, or a variable and its value, like `CI=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+}
+
+impl When {
+    /// Returns the first condition that does not hold
+    pub fn failed(&self) -> Option<String> {
+        if let Some(os) = &self.os {
+            if os != env::consts::OS && os != env::consts::FAMILY {
+                return Some(format!("os: {}", os));
+            }
+        }
+        if let Some(host) = &self.host {
+            let matches = match (glob::Pattern::new(host), hostname()) {
+                (Ok(pattern), Some(hostname)) => pattern.matches(&hostname),
+                _ => false,
+            };
+            if !matches {
+                return Some(format!("host: {}", host));
+            }
+        }
+        if let Some(variable) = &self.env {
+            let matches = match variable.split_once('=') {
+                Some((variable, value)) => env::var(variable).is_ok_and(|actual| actual == value),
+                None => env::var_os(variable).is_some_and(|value| !value.is_empty()),
+            };
+            if !matches {
+                return Some(format!("env: {}", variable));
+            }
+        }
+        None
+    }
+}
+
+impl Tab {
+    pub fn url(&self) -> &str {
+        match self {
+            Tab::Url(url) | Tab::Detailed { url, .. } => url,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Tab::Detailed {
+                url,
+                when: Some(when),
+            } => match when.failed() {
+                Some(condition) => {
+                    log!("Skipping {}, because of `{}`", url, condition);
+                    false
+                }
+                None => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// A lighter alternative to opening a workspace
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OnEnter {
+    /// Set on entering and cleared on leaving
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub local: Vec<Command>,
+}
+
+/// Commands that run to completion, unlike the commands in `Commands`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Run before anything else is opened
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pre_open: Vec<String>,
+    /// Run after the external and background commands and the tabs have started. The shell
+    /// runs local commands as soon as `ws` prints them, so they can still be running.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub post_open: Vec<String>,
+    /// Run by `ws close`
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub on_leave: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub on_failure: OnFailure,
+}
+
+/// What happens when a hook fails
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFailure {
+    /// Stops opening or closing the workspace
+    #[default]
+    Abort,
+    Continue,
+    /// Asks whether to continue
+    Prompt,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Terminal {
+    /// Runs external commands in the windows of a tmux session
+    Tmux,
+    /// Runs external commands with an argument list containing `{path}` and `{command}`
+    #[serde(untagged)]
+    Template(Vec<String>),
+}
+
+impl<'de> serde::Deserialize<'de> for Terminal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TerminalVisitor;
+
+        impl<'de> Visitor<'de> for TerminalVisitor {
+            type Value = Terminal;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("`tmux` or a list of arguments")
+            }
+
+            fn visit_str<E: de::Error>(self, terminal: &str) -> Result<Terminal, E> {
+                match terminal {
+                    "tmux" => Ok(Terminal::Tmux),
+                    _ => Err(E::unknown_variant(terminal, &["tmux"])),
+                }
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, seq: S) -> Result<Terminal, S::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(Terminal::Template)
+            }
+        }
+
+        deserializer.deserialize_any(TerminalVisitor)
+    }
 }
 
 impl Workspace {
-    pub fn open(&self, dir_only: bool) {
+    /// Opens the workspace, returns false if a `pre_open` hook aborted it
+    pub fn open(&self, name: &str, dir_only: bool) -> bool {
+        if !dir_only && !self.run_hooks(&self.hooks.pre_open) {
+            return false;
+        }
+
         run!("cd {}", self.path.display());
+        set_env!("WORKSPACE_NAME", name);
+        set_env!("WORKSPACE_PATH", self.path.display());
         if dir_only {
-            return;
+            return true;
         }
 
-        for command in &self.commands.local {
-            run!("{}", command);
-        }
+        self.run_local(&self.commands.local);
 
-        if !self.commands.external.is_empty() {
-            if let Ok(terminal) = env::var("TERMINAL") {
-                for command in &self.commands.external {
-                    let result = process::Command::new(&terminal)
-                        .arg(command)
-                        .current_dir(&self.path)
-                        .stdin(Stdio::null())
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .spawn();
+        let external: Vec<&Command> = self
+            .commands
+            .external
+            .iter()
+            .filter(|command| command.is_enabled(&self.path))
+            .collect();
+        // Attaching blocks the shell, so it happens after everything else
+        let mut session = None;
+        if !external.is_empty() {
+            match terminal::resolve(self.terminal.as_ref()) {
+                Some(Terminal::Tmux) => session = self.open_tmux(name, &external),
+                Some(Terminal::Template(template)) => {
+                    for command in external {
+                        let dir = command.dir(&self.path);
+                        let result = terminal::command(&template, &dir, &command.line())
+                            .stdin(Stdio::null())
+                            .stdout(Stdio::null())
+                            .stderr(Stdio::null())
+                            .spawn();
 
-                    if result.is_err() {
-                        error!("Could not run command: {}", command);
-                        log!("{}", result.unwrap_err());
+                        if let Err(error) = result {
+                            error!("Could not run command: {}", command.label());
+                            log!("{}", error);
+                        }
                     }
                 }
-            } else {
-                error!("Please set $TERMINAL to run external commands");
+                None => error!("Please set $TERMINAL to run external commands"),
             }
         }
 
+        // `ws status` shows commands that exited until the workspace is opened again
+        service::prune(name);
         if !&self.commands.background.is_empty() {
             if let Ok(shell) = env::var("SHELL") {
-                for command in &self.commands.background {
-                    let result = process::Command::new(&shell)
-                        .arg("-c")
-                        .arg(command)
-                        .current_dir(&self.path)
-                        .stdin(Stdio::null())
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .spawn();
-
-                    if result.is_err() {
-                        error!("Could not run command: {}", command);
-                        log!("{}", result.unwrap_err());
-                    }
-                }
+                self.start_background(name, &shell);
             } else {
                 error!("Please set $SHELL to run commands in the background.");
             }
         }
 
-        if !self.tabs.is_empty() {
-            if let Ok(browser) = env::var("BROWSER") {
-                for tab in &self.tabs {
-                    let result = process::Command::new(&browser)
-                        .arg(tab)
-                        .stdin(Stdio::null())
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .spawn();
+        if !self.watch.is_empty() {
+            self.start_watching(name);
+        }
+
+        let tabs: Vec<&str> = self
+            .tabs
+            .iter()
+            .filter(|tab| tab.is_enabled())
+            .map(Tab::url)
+            .collect();
+        if !tabs.is_empty() {
+            for mut process in browser::commands(self.browser.as_ref(), &tabs) {
+                let result = process
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+
+                if let Err(error) = result {
+                    error!("Could not open tabs with {:?}", process.get_program());
+                    log!("{}", error)
+                }
+            }
+        }
+
+        self.run_hooks(&self.hooks.post_open);
+
+        if let Some(session) = session {
+            if env::var_os("TMUX").is_some() {
+                run!("tmux switch-client -t '={}'", session);
+            } else {
+                run!("tmux attach-session -t '={}'", session);
+            }
+        }
+        true
+    }
+
+    /// Runs commands in the current shell, returning to the workspace path if they have a `cwd`
+    fn run_local(&self, commands: &[Command]) {
+        for command in commands {
+            skip!(!command.is_enabled(&self.path));
+            let dir = command.dir(&self.path);
+            if dir == self.path {
+                run!("{}", command.line());
+            } else {
+                run!("cd {}", dir.display());
+                run!("{}", command.line());
+                run!("cd {}", self.path.display());
+            }
+        }
+    }
+
+    /// Applies `on_enter` after the shell changed into the workspace directory
+    pub fn enter(&self) {
+        for (variable, value) in &self.on_enter.env {
+            // Remembers the previous value for leave(), with a `=` prefix if it was set
+            let saved =
+                original_value(variable).map_or(String::new(), |value| format!("={}", value));
+            set_env!(saved_variable(variable), saved);
+            set_env!(variable, value);
+        }
+        let dir = env::current_dir().unwrap_or_else(|_| self.path.clone());
+        self.run_local(&self.on_enter.local);
+        // Local commands with a `cwd` return to the workspace path, but this is not `ws open`
+        if dir != self.path
+            && self
+                .on_enter
+                .local
+                .iter()
+                .any(|command| command.dir(&self.path) != self.path)
+        {
+            run!("cd {}", dir.display());
+        }
+    }
 
-                    if result.is_err() {
-                        error!("Could not open tab: {}", tab);
-                        log!("{}", result.unwrap_err())
+    /// Restores the variables of `on_enter` after the shell left the workspace directory
+    pub fn leave(&self) {
+        for variable in self.on_enter.env.keys() {
+            match original_value(variable) {
+                Some(value) => set_env!(variable, value),
+                None => unset_env!(variable),
+            }
+            unset_env!(saved_variable(variable));
+        }
+    }
+
+    /// Returns whether the workspace is in the user's own folder rather than in `search_paths`,
+    /// which can be shared with others
+    pub fn is_own(name: &str) -> bool {
+        Self::file_path(name).parent() == Some(&Self::folder_path())
+    }
+
+    /// Starts the background commands after their dependencies are ready. The output of
+    /// services is written to a log file, which `wait_for_log` reads.
+    fn start_background(&self, name: &str, shell: &str) {
+        let ordered = match service::order(&self.commands.background) {
+            Ok(ordered) => ordered,
+            Err(message) => {
+                error!("{}", message);
+                return;
+            }
+        };
+        let mut ready: Vec<&str> = Vec::new();
+        let mut failed: Vec<&str> = Vec::new();
+        let mut started: Vec<(&Command, PathBuf)> = Vec::new();
+
+        for (index, command) in ordered.into_iter().enumerate() {
+            for dependency in command.depends_on() {
+                skip!(ready.contains(&dependency.as_str()));
+                let started = started
+                    .iter()
+                    .find(|(other, _)| other.name() == Some(dependency.as_str()));
+                let is_ready = match started {
+                    Some((other, log)) => {
+                        eprintln!("Waiting for '{}' to be ready", dependency);
+                        service::wait(other, &self.path, log)
                     }
+                    None => false,
+                };
+                if is_ready {
+                    ready.push(dependency);
+                } else if !failed.contains(&dependency.as_str()) {
+                    error!(
+                        "The background command '{}' did not become ready",
+                        dependency
+                    );
+                    failed.push(dependency);
+                }
+            }
+            if let Some(dependency) = command
+                .depends_on()
+                .iter()
+                .find(|dependency| failed.contains(&dependency.as_str()))
+            {
+                error!(
+                    "Not running `{}`, because '{}' failed",
+                    command.label(),
+                    dependency
+                );
+                if let Some(name) = command.name() {
+                    failed.push(name);
+                }
+                continue;
+            }
+            if !command.is_enabled(&self.path) || !Self::free_ports(name, command) {
+                if let Some(name) = command.name() {
+                    failed.push(name);
                 }
+                continue;
+            }
+
+            let log = match command {
+                Command::Detailed(_) => {
+                    let command_name = command.name().map_or(index.to_string(), str::to_string);
+                    service::log_path(name, &command_name)
+                }
+                Command::Plain(_) => None,
+            };
+            let output = log.as_ref().and_then(|log| fs::File::create(log).ok());
+            let (stdout, stderr) = match output
+                .as_ref()
+                .map(|file| (file.try_clone(), file.try_clone()))
+            {
+                Some((Ok(stdout), Ok(stderr))) => (Stdio::from(stdout), Stdio::from(stderr)),
+                _ => (Stdio::null(), Stdio::null()),
+            };
+
+            let shell = command
+                .details()
+                .and_then(|details| details.shell.as_deref())
+                .unwrap_or(shell);
+            let restart = command
+                .details()
+                .map_or(Restart::Never, |details| details.restart);
+            let mut process = if restart == Restart::Never {
+                let mut process = process::Command::new(shell);
+                process.arg("-c").arg(command.run());
+                process
             } else {
-                error!("Please set $BROWSER to open browser tabs")
+                // Supervisors run this binary again, which writes crashes to the log
+                let exe = env::current_exe().unwrap_or_else(|_| PathBuf::from("workspace"));
+                let mut process = process::Command::new(exe);
+                process.arg("supervise").arg(shell).arg(command.run());
+                if restart == Restart::Always {
+                    process.arg("--always");
+                }
+                process
+            };
+            process
+                .current_dir(command.dir(&self.path))
+                .envs(command.env())
+                .stdin(Stdio::null())
+                .stdout(stdout)
+                .stderr(stderr);
+            // A process group of its own allows `ws close` to stop the command with its children
+            #[cfg(unix)]
+            std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+            match process.spawn() {
+                Ok(child) => {
+                    let process =
+                        service::Process::new(child.id(), command.is_service(), command.label());
+                    service::track(name, &process);
+                    started.push((command, log.unwrap_or_default()));
+                }
+                Err(error) => {
+                    error!("Could not run command: {}", command.label());
+                    log!("{}", error);
+                    if let Some(name) = command.name() {
+                        failed.push(name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that the ports of a background command are free and offers to close the
+    /// workspace using them, returns whether the command can start
+    fn free_ports(name: &str, command: &Command) -> bool {
+        let ports = command.details().map_or(&[][..], |details| &details.ports);
+        for &port in ports {
+            skip!(service::is_port_free(port));
+            let other = match service::port_owner(port) {
+                Some(other) if other != name => other,
+                Some(_) => {
+                    error!(
+                        "Not running `{}`, because this workspace already uses port {}",
+                        command.label(),
+                        port
+                    );
+                    return false;
+                }
+                None => {
+                    error!(
+                        "Not running `{}`, because port {} is already in use",
+                        command.label(),
+                        port
+                    );
+                    return false;
+                }
+            };
+
+            warn!("Port {} is used by the workspace '{}'", port, other);
+            if !ask!("Close '{}' to run `{}`", other, command.label()) {
+                return false;
+            }
+            let (base, branch) = Self::split_branch(&other);
+            let closed = match Self::get(base) {
+                Some(Ok(mut ws)) => {
+                    if let Some(path) = branch.and_then(|branch| git::worktree(&ws.path, branch)) {
+                        ws.path = path;
+                    }
+                    ws.close(&other)
+                }
+                // The workspace is gone, but its processes can still be stopped
+                _ => service::stop(&other) > 0,
+            };
+            if !closed || !service::wait_for_free_port(port) {
+                error!("Could not free port {} for `{}`", port, command.label());
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs `ws watch` in the background, which `ws close` stops like a background command
+    fn start_watching(&self, name: &str) {
+        let exe = env::current_exe().unwrap_or_else(|_| PathBuf::from("workspace"));
+        let output = service::log_path(name, "watch").and_then(|log| fs::File::create(log).ok());
+        let (stdout, stderr) = match output
+            .as_ref()
+            .map(|file| (file.try_clone(), file.try_clone()))
+        {
+            Some((Ok(stdout), Ok(stderr))) => (Stdio::from(stdout), Stdio::from(stderr)),
+            _ => (Stdio::null(), Stdio::null()),
+        };
+        let mut process = process::Command::new(exe);
+        process
+            .arg("watch")
+            .arg(name)
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr);
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+        match process.spawn() {
+            Ok(child) => {
+                let process = service::Process::new(child.id(), true, "ws watch");
+                service::track(name, &process);
+            }
+            Err(error) => {
+                error!("Could not watch files");
+                log!("{}", error);
             }
         }
     }
 
+    /// Runs the `on_leave` hooks, stops the background commands and kills the tmux session,
+    /// returns whether it was closed
+    pub fn close(&self, name: &str) -> bool {
+        if !self.run_hooks(&self.hooks.on_leave) {
+            return false;
+        }
+        let stopped = service::stop(name);
+        if stopped > 0 {
+            log!("Stopped {} background command(s)", stopped);
+        }
+        let session = tmux::session_name(name);
+        if tmux::has_session(&session) {
+            tmux::kill_session(&session);
+        }
+        true
+    }
+
+    /// Runs hooks one after another and returns whether to go on, according to `on_failure`
+    fn run_hooks(&self, hooks: &[String]) -> bool {
+        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        for hook in hooks {
+            let status = process::Command::new(&shell)
+                .arg("-c")
+                .arg(hook)
+                .current_dir(&self.path)
+                .stdin(editor::tty())
+                .stdout(editor::tty())
+                .status();
+
+            match status {
+                Ok(status) if status.success() => continue,
+                Ok(status) => error!("The hook `{}` failed with {}", hook, status),
+                Err(error) => {
+                    error!("Could not run hook: {}", hook);
+                    log!("{}", error);
+                }
+            }
+            let go_on = match self.hooks.on_failure {
+                OnFailure::Abort => false,
+                OnFailure::Continue => true,
+                OnFailure::Prompt => ask!("Continue anyway"),
+            };
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs each external command in a window of the workspace's tmux session and returns the
+    /// session to attach to
+    fn open_tmux(&self, name: &str, commands: &[&Command]) -> Option<String> {
+        let session = tmux::session_name(name);
+        if !tmux::has_session(&session) {
+            let mut commands = commands.iter();
+            // Safe to unwrap here, because open() only calls this with external commands
+            let first = commands.next().unwrap();
+            if !tmux::new_session(&session, &first.dir(&self.path), &first.line()) {
+                error!("Could not create tmux session '{}'", session);
+                return None;
+            }
+            for command in commands {
+                if !tmux::new_window(&session, &command.dir(&self.path), &command.line()) {
+                    error!("Could not run command: {}", command.label());
+                }
+            }
+        }
+        Some(session)
+    }
+
+    /// Returns where a worktree for `branch` is created, by default next to the workspace
+    pub fn worktree_path(&self, branch: &str) -> PathBuf {
+        let mut path = match &self.worktrees {
+            Some(worktrees) => self.path.join(worktrees),
+            None => {
+                let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+                name.push(".worktrees");
+                self.path.with_file_name(name)
+            }
+        };
+        path.push(branch.replace('/', "-"));
+        path
+    }
+
     pub fn write(&self, name: &str) {
         const ERR_MESSAGE: &str = "Could not write workspace data";
 
@@ -115,6 +934,7 @@ impl Workspace {
             .read(false)
             .write(true)
             .create(true)
+            .truncate(true)
             .open(path)
             .unwrap_or_exit(ERR_MESSAGE);
 
@@ -123,12 +943,36 @@ impl Workspace {
             .unwrap_or_exit(ERR_MESSAGE);
     }
 
+    /// Changes the path in the file of the workspace, keeping the rest of it as written, e.g.
+    /// comments and `$VAR`
+    pub fn write_path(&mut self, name: &str, path: PathBuf) {
+        self.path = path;
+        let file = Self::file_path(name);
+        let content = Self::read(&file).unwrap_or_exit("Could not read workspace data");
+        let mut line = BTreeMap::new();
+        line.insert("path", self.path.tilde_format());
+        let line = serde_yaml::to_string(&line).unwrap();
+        match replace_path(&content, line.trim_start_matches("---\n").trim_end()) {
+            Some(content) => {
+                fs::write(&file, content).unwrap_or_exit("Could not write workspace data")
+            }
+            None => {
+                warn!("Could not find the path in {}", file.tilde_format());
+                indent_warn!("rewriting the whole file, which drops its comments");
+                self.write(name);
+            }
+        }
+    }
+
     pub fn edit(name: &str) {
         let path = Self::file_path(name);
-        let editor = env::var("EDITOR").unwrap_or_else(|_| {
-            env::var("VISUAL").unwrap_or_exit("Please set $EDITOR or $VISUAL to edit workspaces")
+        editor::edit(&path, |temp| match Self::parse(temp) {
+            Ok(_) => true,
+            Err(error) => {
+                error.report();
+                false
+            }
         });
-        run!("{} {}", editor, path.display());
     }
 
     pub fn delete(name: &str) {
@@ -136,19 +980,56 @@ impl Workspace {
         fs::remove_file(path).unwrap_or_exit("Could not delete workspace data");
     }
 
+    /// Splits `NAME@BRANCH` into the name and branch. Names can contain @, so this only happens
+    /// if there is no workspace with the whole name.
+    pub fn split_branch(name: &str) -> (&str, Option<&str>) {
+        match name.rsplit_once('@') {
+            Some((base, branch)) if !Self::exists(name) => (base, Some(branch)),
+            _ => (name, None),
+        }
+    }
+
     pub fn exists(name: &str) -> bool {
-        Self::file_path(name).exists()
+        let path = Self::file_path(name);
+        path.exists() && !Self::is_reserved(name)
+    }
+
+    /// Returns whether a workspace would be stored in the configuration file
+    pub fn is_reserved(name: &str) -> bool {
+        Self::file_path(name) == Config::file_path()
     }
 
     pub fn get(name: &str) -> Option<Result<Workspace, Error>> {
         let path = Self::file_path(name);
-        if path.exists() {
+        if Self::exists(name) {
             Some(Self::parse(&path))
         } else {
             None
         }
     }
 
+    /// Returns the workspace containing the current directory, the innermost if they are nested.
+    /// Paths are compared as they are and with symlinks resolved.
+    pub fn current() -> Option<(String, Workspace)> {
+        let dir = env::current_dir().ok()?;
+        let dirs = [dir.canonicalize().ok(), Some(dir)];
+        Self::all()
+            .into_iter()
+            .filter_map(|(name, result)| {
+                let ws = result.ok()?;
+                let paths = [ws.path.canonicalize().ok(), Some(ws.path.clone())];
+                let depth = paths
+                    .iter()
+                    .flatten()
+                    .filter(|path| dirs.iter().flatten().any(|dir| dir.starts_with(path)))
+                    .map(|path| path.components().count())
+                    .max()?;
+                Some((depth, name?, ws))
+            })
+            .max_by_key(|(depth, _, _)| *depth)
+            .map(|(_, name, ws)| (name, ws))
+    }
+
     pub fn all() -> Vec<(Option<String>, Result<Workspace, Error>)> {
         Self::paths()
             .into_iter()
@@ -161,75 +1042,118 @@ impl Workspace {
             .collect()
     }
 
-    fn parse(path: &PathBuf) -> Result<Workspace, Error> {
-        let content: String = Self::read(&path)?;
-        let ws: Workspace = serde_yaml::from_str(&content)?;
-        Ok(ws)
+    pub fn parse(path: &Path) -> Result<Workspace, Error> {
+        let content: String = Self::read(path).map_err(|cause| Error::Read {
+            path: path.to_path_buf(),
+            cause,
+        })?;
+        serde_yaml::from_str(&content).map_err(|cause| Error::parse(path, &content, cause))
     }
 
-    fn read(path: &PathBuf) -> io::Result<String> {
+    fn read(path: &Path) -> io::Result<String> {
         let mut content: String = String::new();
 
         fs::OpenOptions::new()
             .read(true)
-            .open(&path)?
+            .open(path)?
             .read_to_string(&mut content)?;
 
         Ok(content)
     }
 
+    /// Returns the paths of all workspace files. If several folders contain a workspace with
+    /// the same name, the first one is used.
     fn paths() -> Vec<PathBuf> {
-        let entries =
-            fs::read_dir(Self::folder_path()).unwrap_or_exit("Could not find workspace data");
         let mut paths: Vec<PathBuf> = Vec::new();
 
-        for entry in entries {
-            skip_err!(entry);
-            let entry = entry.unwrap();
-            let path = entry.path();
-
-            skip_err!(entry.file_type());
-            let file_type = entry.file_type().unwrap();
-            skip!(
-                !file_type.is_file(),
-                format!("Skipping {} because it's not a file", path.tilde_format())
-            );
-
-            skip_none!(
-                path.extension(),
-                format!(
-                    "Skipping {} because it has no file extension",
-                    path.tilde_format()
-                )
-            );
-            let extension = path.extension().unwrap();
-            skip!(
-                extension.to_string_lossy() != "yaml",
-                format!(
-                    "Skipping {} because it's not a YAML file",
-                    path.tilde_format()
-                )
-            );
-
-            paths.push(entry.path());
+        for folder in Self::folder_paths() {
+            let entries = fs::read_dir(&folder);
+            skip_err!(entries);
+            let entries = entries.unwrap();
+
+            for entry in entries {
+                skip_err!(entry);
+                let entry = entry.unwrap();
+                let path = entry.path();
+
+                skip_err!(entry.file_type());
+                let file_type = entry.file_type().unwrap();
+                skip!(
+                    !file_type.is_file(),
+                    format!("Skipping {} because it's not a file", path.tilde_format())
+                );
+
+                skip_none!(
+                    path.extension(),
+                    format!(
+                        "Skipping {} because it has no file extension",
+                        path.tilde_format()
+                    )
+                );
+                let extension = path.extension().unwrap();
+                skip!(
+                    extension.to_string_lossy() != "yaml",
+                    format!(
+                        "Skipping {} because it's not a YAML file",
+                        path.tilde_format()
+                    )
+                );
+                skip!(
+                    path == Config::file_path(),
+                    format!(
+                        "Skipping {} because it's the configuration",
+                        path.tilde_format()
+                    )
+                );
+                skip!(
+                    paths
+                        .iter()
+                        .any(|other| other.file_name() == path.file_name()),
+                    format!(
+                        "Skipping {} because a workspace with the same name comes first",
+                        path.tilde_format()
+                    )
+                );
+
+                paths.push(entry.path());
+            }
         }
 
         paths
     }
 
+    /// Returns the path of an existing workspace file, or where a new one is created
     pub fn file_path(name: &str) -> PathBuf {
-        let mut path = Self::folder_path();
-        path.push(name);
-        path.set_extension("yaml");
-        path
+        let file_path = |mut path: PathBuf| {
+            path.push(name);
+            path.set_extension("yaml");
+            path
+        };
+        Self::folder_paths()
+            .into_iter()
+            .map(file_path)
+            .find(|path| path.exists())
+            .unwrap_or_else(|| file_path(Self::folder_path()))
     }
 
+    /// Returns the folder in which new workspaces are created: $WORKSPACE_DIR, the `directory`
+    /// setting or the configuration directory
     fn folder_path() -> PathBuf {
-        let mut path = dirs::config_dir().unwrap_or_exit("Could not find configuration directory");
-        path.push("workspace");
+        let path = match env::var_os("WORKSPACE_DIR") {
+            Some(directory) => PathBuf::from(directory),
+            None => match Config::get().directory() {
+                Some(directory) => directory,
+                None => {
+                    let mut path =
+                        dirs::config_dir().unwrap_or_exit("Could not find configuration directory");
+                    path.push("workspace");
+                    path
+                }
+            },
+        };
 
         if !path.exists() {
-            fs::create_dir(&path).unwrap_or_exit(&format!(
+            fs::create_dir_all(&path).unwrap_or_exit(&format!(
                 "Could not create directory {}",
                 path.tilde_format()
             ));
@@ -237,28 +1161,348 @@ impl Workspace {
 
         path
     }
+
+    /// Returns the folder for new workspaces, followed by the `search_paths` setting
+    pub fn folder_paths() -> Vec<PathBuf> {
+        let mut paths = vec![Self::folder_path()];
+        paths.extend(Config::get().search_paths());
+        paths
+    }
 }
 
-#[derive(Fail, Debug)]
+#[derive(Debug)]
 pub enum Error {
-    #[fail(display = "Could not read workspace data")]
-    Read(#[cause] io::Error),
-    #[fail(display = "Could not parse workspace data")]
-    Parse(#[cause] serde_yaml::Error),
+    Read {
+        path: PathBuf,
+        cause: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        /// Line and column, both starting at 1, and the content of that line
+        location: Option<(usize, usize, String)>,
+        cause: serde_yaml::Error,
+    },
 }
 
-impl From<io::Error> for Error {
-    fn from(cause: io::Error) -> Error {
-        Error::Read(cause)
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Read { .. } => write!(f, "Could not read workspace data"),
+            Error::Parse { .. } => write!(f, "Could not parse workspace data"),
+        }
     }
 }
 
-impl From<serde_yaml::Error> for Error {
-    fn from(cause: serde_yaml::Error) -> Error {
-        Error::Parse(cause)
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read { cause, .. } => Some(cause),
+            Error::Parse { cause, .. } => Some(cause),
+        }
     }
 }
 
+impl Error {
+    fn parse(path: &Path, content: &str, cause: serde_yaml::Error) -> Error {
+        let location = cause.location().and_then(|location| {
+            let snippet = content.lines().nth(location.line() - 1)?;
+            Some((location.line(), location.column(), snippet.to_string()))
+        });
+        Error::Parse {
+            path: path.to_path_buf(),
+            location,
+            cause,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            Error::Read { path, .. } | Error::Parse { path, .. } => path,
+        }
+    }
+
+    /// Prints the error with its cause and, if possible, the offending line and a hint
+    pub fn report(&self) {
+        error!("{} from {}", self, self.path().tilde_format());
+        match self {
+            Error::Read { cause, .. } => indent_error!("{}", cause),
+            Error::Parse {
+                location: None,
+                cause,
+                ..
+            } => indent_error!("{}", cause),
+            Error::Parse {
+                location: Some((line, column, snippet)),
+                cause,
+                ..
+            } => {
+                // The message of serde_yaml ends with the location, which is printed separately
+                let message = cause.to_string();
+                let suffix = format!(" at line {} column {}", line, column);
+                let message = message.trim_end_matches(suffix.as_str());
+                indent_error!("{}", message);
+
+                let number = line.to_string();
+                let margin = " ".repeat(number.len());
+                indent_error!("{} {}", margin, "|".bright_blue().bold());
+                indent_error!(
+                    "{} {} {}",
+                    number.bright_blue().bold(),
+                    "|".bright_blue().bold(),
+                    snippet
+                );
+                indent_error!(
+                    "{} {} {}{}",
+                    margin,
+                    "|".bright_blue().bold(),
+                    " ".repeat(column - 1),
+                    "^".red().bold()
+                );
+                if let Some(suggestion) = suggestion(message) {
+                    indent_error!(
+                        "{} {} did you mean `{}`?",
+                        margin,
+                        "= hint:".bold(),
+                        suggestion
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Suggests a known field for serde's "unknown field `x`, expected one of `a`, `b`" messages,
+/// whose list is generated from the fields of `Workspace` and `Commands`
+fn suggestion(message: &str) -> Option<&str> {
+    // Errors in nested fields are prefixed with their path, e.g. `commands: unknown field ...`
+    let start = message
+        .find("unknown field")
+        .or_else(|| message.find("unknown variant"))?;
+    // Every other piece is quoted in backticks
+    let mut quoted = message[start..].split('`').skip(1).step_by(2);
+    let unknown = quoted.next()?;
+    quoted
+        .map(|known| (distance(unknown, known), known))
+        .filter(|(distance, known)| *distance <= std::cmp::max(2, known.len() / 3))
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Computes the Levenshtein distance between two strings
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 fn is_default<T: Default + PartialEq>(t: &T) -> bool {
     t == &T::default()
 }
+
+/// Returns the variable in which `enter()` saves the previous value of a variable
+fn saved_variable(variable: &str) -> String {
+    format!("WORKSPACE_SAVED_{}", variable)
+}
+
+/// Returns the value a variable had before a workspace was entered, or `None` if it was unset
+fn original_value(variable: &str) -> Option<String> {
+    match env::var(saved_variable(variable)) {
+        Ok(saved) => saved.strip_prefix('=').map(str::to_string),
+        Err(_) => env::var(variable).ok(),
+    }
+}
+
+/// Returns the name of this computer from $HOSTNAME or the `hostname` program
+fn hostname() -> Option<String> {
+    if let Ok(hostname) = env::var("HOSTNAME") {
+        return Some(hostname);
+    }
+    let output = process::Command::new("hostname")
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let hostname = String::from_utf8(output.stdout).ok()?;
+    Some(hostname.trim().to_string())
+}
+
+/// Quotes a string for POSIX shells and fish
+pub fn quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "'\\''"))
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn is_default_timeout(timeout: &u64) -> bool {
+    *timeout == default_timeout()
+}
+
+/// Replaces the top-level `path` entry of a workspace file with `line`, along with the indented
+/// lines that continue its value
+fn replace_path(content: &str, line: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|line| line.starts_with("path:"))?;
+    let end = start
+        + 1
+        + lines[start + 1..]
+            .iter()
+            .take_while(|line| {
+                line.starts_with([' ', '\t']) && !line.trim_start().starts_with('#')
+            })
+            .count();
+
+    let mut replaced = lines[..start].to_vec();
+    replaced.push(line);
+    replaced.extend(&lines[end..]);
+    Some(replaced.join("\n") + "\n")
+}
+
+fn expand<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    PathBuf::deserialize(deserializer).map(|path| path.tilde_expand())
+}
+
+/// Like `expand`, but resolves relative paths against the home directory rather than the
+/// directory `ws` happens to run in
+fn expand_from_home<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    expand(deserializer).map(|path| match dirs::home_dir() {
+        Some(home) if path.is_relative() => home.join(path),
+        _ => path,
+    })
+}
+
+/// Writes paths below the home directory with `~`, so that they work for other users
+fn contract<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.tilde_format())
+}
+
+fn expand_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error> {
+    expand(deserializer).map(Some)
+}
+
+fn contract_option<S: Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => contract(path, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("tabs", "tabs"), 0);
+        assert_eq!(distance("tab", "tabs"), 1);
+        assert_eq!(distance("commnads", "commands"), 2);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn worktrees() {
+        let home = dirs::home_dir().unwrap();
+        let ws: Workspace = serde_yaml::from_str("path: ~/blog\nworktrees: ~/wt").unwrap();
+        assert_eq!(ws.worktrees, Some(home.join("wt")));
+        assert_eq!(
+            ws.worktree_path("feature/x"),
+            home.join("wt").join("feature-x")
+        );
+        let serialized = serde_yaml::to_string(&ws).unwrap();
+        assert!(serialized.contains("worktrees: ~/wt"), "{}", serialized);
+
+        let ws: Workspace = serde_yaml::from_str("path: ~/blog\nworktrees: ../wt").unwrap();
+        assert_eq!(ws.worktree_path("main"), home.join("blog/../wt/main"));
+
+        let ws: Workspace = serde_yaml::from_str("path: blog").unwrap();
+        assert_eq!(ws.path, home.join("blog"));
+        assert_eq!(ws.worktrees, None);
+        assert_eq!(ws.worktree_path("main"), home.join("blog.worktrees/main"));
+    }
+
+    #[test]
+    fn replace_path_line() {
+        let content = "# My blog\npath: $CODE/blog # moved\ntabs:\n  - localhost\n";
+        assert_eq!(
+            replace_path(content, "path: ~/blog").as_deref(),
+            Some("# My blog\npath: ~/blog\ntabs:\n  - localhost\n")
+        );
+
+        let content = "path:\n  ~/old\n  # comment\nrepo: a.git\n";
+        assert_eq!(
+            replace_path(content, "path: ~/blog").as_deref(),
+            Some("path: ~/blog\n  # comment\nrepo: a.git\n")
+        );
+
+        assert_eq!(replace_path("{path: ~/old}", "path: ~/blog"), None);
+    }
+
+    #[test]
+    fn string_or_table() {
+        let commands: Vec<Command> =
+            serde_yaml::from_str("- make\n- {run: make test, cwd: src}").unwrap();
+        assert_eq!(commands[0], Command::Plain("make".to_string()));
+        assert_eq!(commands[1].run(), "make test");
+
+        let error = serde_yaml::from_str::<Vec<Command>>("- {run: make, cwdd: src}").unwrap_err();
+        assert!(
+            error.to_string().contains("unknown field `cwdd`"),
+            "{}",
+            error
+        );
+        assert_eq!(suggestion(&error.to_string()), Some("cwd"));
+
+        let error = serde_yaml::from_str::<Vec<Tab>>("- {url: a.com, wen: {}}").unwrap_err();
+        assert_eq!(suggestion(&error.to_string()), Some("when"));
+
+        let terminal: Terminal = serde_yaml::from_str("[kitty, -d, '{path}']").unwrap();
+        assert!(matches!(terminal, Terminal::Template(arguments) if arguments.len() == 3));
+        assert_eq!(
+            serde_yaml::from_str::<Terminal>("tmux").unwrap(),
+            Terminal::Tmux
+        );
+        let error = serde_yaml::from_str::<Terminal>("tmxu").unwrap_err();
+        assert_eq!(suggestion(&error.to_string()), Some("tmux"));
+    }
+
+    #[test]
+    fn services() {
+        let commands: Vec<Command> = serde_yaml::from_str(
+            "- make\n\
+             - {run: make, description: Build}\n\
+             - {run: make, wait_for_file: out}\n\
+             - {run: server, ports: [8080]}\n\
+             - {run: server, restart: on-failure}\n\
+             - {run: server, wait_for_log: Listening}",
+        )
+        .unwrap();
+        let services: Vec<bool> = commands.iter().map(Command::is_service).collect();
+        assert_eq!(services, [false, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn suggestions() {
+        let message = "unknown field `tab`, expected one of `path`, `tabs`, `commands`";
+        assert_eq!(suggestion(message), Some("tabs"));
+        let message =
+            "commands: unknown field `locl`, expected one of `local`, `external`, `background`";
+        assert_eq!(suggestion(message), Some("local"));
+        let message = "unknown variant `abrot`, expected one of `abort`, `continue`, `prompt`";
+        assert_eq!(suggestion(message), Some("abort"));
+        let message = "unknown field `zzzzzzzz`, expected one of `path`, `tabs`";
+        assert_eq!(suggestion(message), None);
+        assert_eq!(suggestion("invalid type: map, expected a string"), None);
+    }
+}
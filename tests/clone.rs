@@ -0,0 +1,88 @@
+mod common;
+
+use common::temp_dir;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn open_clones_missing_path() {
+    let root = temp_dir("clone");
+    let remote = root.join("remote.git");
+    fs::create_dir_all(&remote).unwrap();
+    git(&remote, &["init", "--bare", "--quiet"]);
+
+    let work = root.join("work");
+    fs::create_dir_all(&work).unwrap();
+    git(&work, &["init", "--quiet"]);
+    fs::write(work.join("README.md"), "hello\n").unwrap();
+    git(&work, &["add", "README.md"]);
+    git(
+        &work,
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "init",
+        ],
+    );
+    git(
+        &work,
+        &["push", "--quiet", remote.to_str().unwrap(), "HEAD"],
+    );
+
+    let workspaces = root.join("workspaces");
+    fs::create_dir_all(&workspaces).unwrap();
+    let path = root.join("missing").join("project");
+    fs::write(
+        workspaces.join("project.yaml"),
+        format!("path: {}\nrepo: {}\n", path.display(), remote.display()),
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_workspace"))
+        .args(["--from-shell-wrapper", "open", "project"])
+        .env("WORKSPACE_DIR", &workspaces)
+        .env("HOME", &root)
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("XDG_CACHE_HOME", root.join("cache"))
+        .env_remove("WORKSPACE_NAME")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"y\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(path.join("README.md")).unwrap(),
+        "hello\n"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("RUN>cd"), "{}", stdout);
+
+    fs::remove_dir_all(&root).unwrap();
+}
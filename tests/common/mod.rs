@@ -0,0 +1,14 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// Returns an empty directory that is unique to this test run
+pub fn temp_dir(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("workspace-test-{}-{}", process::id(), name));
+    if path.exists() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    fs::create_dir_all(&path).unwrap();
+    path
+}
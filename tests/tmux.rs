@@ -0,0 +1,104 @@
+mod common;
+
+use common::temp_dir;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Runs a command against the private tmux server in `root`
+fn run(command: &mut Command, root: &Path) -> Output {
+    command
+        .env("TMUX_TMPDIR", root.join("tmux"))
+        .env("WORKSPACE_DIR", root.join("workspaces"))
+        .env("HOME", root)
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("XDG_CACHE_HOME", root.join("cache"))
+        .env_remove("TMUX")
+        .env_remove("WORKSPACE_NAME")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn workspace(root: &Path, args: &[&str]) -> Output {
+    let output = run(
+        Command::new(env!("CARGO_BIN_EXE_workspace"))
+            .arg("--from-shell-wrapper")
+            .args(args),
+        root,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn tmux(root: &Path, args: &[&str]) -> Output {
+    run(Command::new("tmux").args(args), root)
+}
+
+#[test]
+fn open_and_close_session() {
+    if Command::new("tmux").arg("-V").output().is_err() {
+        eprintln!("Skipping, because tmux is not installed");
+        return;
+    }
+
+    let root = temp_dir("tmux");
+    fs::create_dir_all(root.join("tmux")).unwrap();
+    fs::create_dir_all(root.join("workspaces")).unwrap();
+    fs::write(
+        root.join("workspaces").join("project.yaml"),
+        format!(
+            "path: {}\n\
+             terminal: tmux\n\
+             commands:\n  \
+               external:\n    \
+                 - sleep 600\n    \
+                 - sleep 601\n\
+             hooks:\n  \
+               post_open:\n    \
+                 - echo opened\n",
+            root.display()
+        ),
+    )
+    .unwrap();
+
+    let output = workspace(&root, &["open", "project"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Attaching blocks the shell, so it comes after the hooks
+    assert_eq!(
+        stdout.lines().last(),
+        Some("RUN>tmux attach-session -t '=project'"),
+        "{}",
+        stdout
+    );
+
+    let windows = tmux(
+        &root,
+        &[
+            "list-windows",
+            "-t",
+            "=project",
+            "-F",
+            "#{pane_start_command}",
+        ],
+    );
+    assert!(windows.status.success());
+    let windows = String::from_utf8_lossy(&windows.stdout);
+    let windows: Vec<&str> = windows.lines().collect();
+    assert_eq!(windows.len(), 2, "{:?}", windows);
+    assert!(windows[0].contains("sleep 600"), "{:?}", windows);
+    assert!(windows[1].contains("sleep 601"), "{:?}", windows);
+
+    workspace(&root, &["close", "project"]);
+    assert!(!tmux(&root, &["has-session", "-t", "=project"])
+        .status
+        .success());
+
+    tmux(&root, &["kill-server"]);
+    fs::remove_dir_all(&root).unwrap();
+}